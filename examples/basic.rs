@@ -120,6 +120,34 @@ fn main() {
     let mut last_cursor = None;
 
     let mut timeline = imgui_timeline_rs::Timeline::new("Basic");
+    for i in 0..20 {
+        let interpolation = match i % 3 {
+            0 => imgui_timeline_rs::Interpolation::Constant,
+            1 => imgui_timeline_rs::Interpolation::Linear,
+            _ => imgui_timeline_rs::Interpolation::EASE_IN_OUT,
+        };
+        let mut track = imgui_timeline_rs::KeyFrameTrack::new(format!("Test Track {i}"))
+            .with_default_interpolation(interpolation);
+        for k in 0..5 {
+            let time = imgui_timeline_rs::Time::from_millis(2000 + k * 2000 + i * 50);
+            track.insert_key(time, k as f32);
+        }
+        timeline.add_track(track);
+    }
+    let mut shots = imgui_timeline_rs::ClipTrack::new("Shots");
+    for (i, label) in ["Wide", "Close up", "Reverse"].into_iter().enumerate() {
+        let start = imgui_timeline_rs::Time::from_millis(2000 + i as i64 * 3000);
+        let length = imgui_timeline_rs::Time::from_millis(2500);
+        shots.insert_clip(start, length, label, [0.3, 0.5, 0.7, 1.0]);
+    }
+    timeline.insert_track(0, shots);
+    let mut cues = imgui_timeline_rs::EventTrack::new("Cues").with_default_payload("cue");
+    cues.insert_event(imgui_timeline_rs::Time::from_millis(2000), "sound:whoosh");
+    cues.insert_event(
+        imgui_timeline_rs::Time::from_millis(5500),
+        "particles:sparks",
+    );
+    let cues = timeline.insert_track(1, cues);
 
     // Event loop
     event_loop.run(move |event, _, control_flow| {
@@ -187,7 +215,15 @@ fn main() {
                     ui.dockspace_over_main_viewport();
                     ui.show_demo_window(&mut demo_open);
                     imgui_timeline_rs::hello(ui);
-                    timeline.draw(ui, delta_s.as_secs_f32());
+                    for crossed in timeline.tick(delta_s.as_secs_f32()) {
+                        if let Some(event) = timeline
+                            .track_as::<imgui_timeline_rs::EventTrack>(cues)
+                            .and_then(|track| track.event(crossed.key.key))
+                        {
+                            println!("{} at {}", event.payload(), crossed.time);
+                        }
+                    }
+                    timeline.draw(ui);
 
                     // let window = ui.window("Hello world");
                     // window
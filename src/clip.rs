@@ -0,0 +1,410 @@
+use std::{any::Any, rc::Rc};
+
+#[cfg(feature = "serde")]
+use crate::TrackData;
+use crate::{
+    clipboard::{escape_field, unescape_field},
+    view::draw_track_name,
+    KeyId, KeyState, PasteError, Time, Track, TrackView,
+};
+
+/// A stretch of time on a [`ClipTrack`], like a camera shot or a line of dialogue.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Clip {
+    id: KeyId,
+    start: Time,
+    length: Time,
+    label: String,
+    color: [f32; 4],
+}
+
+impl Clip {
+    pub fn id(&self) -> KeyId {
+        self.id
+    }
+
+    pub fn start(&self) -> Time {
+        self.start
+    }
+
+    pub fn length(&self) -> Time {
+        self.length
+    }
+
+    pub fn end(&self) -> Time {
+        self.start + self.length
+    }
+
+    pub fn label(&self) -> &str {
+        &self.label
+    }
+
+    pub fn color(&self) -> [f32; 4] {
+        self.color
+    }
+
+    /// Whether `time` lies in the clip, which includes its start but not its end.
+    pub fn contains(&self, time: Time) -> bool {
+        self.start <= time && time < self.end()
+    }
+}
+
+/// A track of clips that never overlap, drawn as bars that can be moved, trimmed at either
+/// edge and split. The clips are the track's keys, keyed by their start.
+pub struct ClipTrack {
+    name: String,
+    clips: Vec<Clip>,
+    next_clip_id: u64,
+    default_length: Time,
+    default_color: [f32; 4],
+}
+
+impl ClipTrack {
+    const TRACK_HEIGHT: f32 = 30.0;
+    const ROUNDING: f32 = 4.0;
+    /// Tag of clip tracks in saved documents.
+    pub const TYPE_TAG: &'static str = "clip";
+
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            clips: Vec::new(),
+            next_clip_id: 0,
+            default_length: Time::from_millis(2000),
+            default_color: [0.3, 0.5, 0.7, 1.0],
+        }
+    }
+
+    /// Length and color of clips created by double-clicking the lane.
+    pub fn with_defaults(mut self, length: Time, color: [f32; 4]) -> Self {
+        self.default_length = length;
+        self.default_color = color;
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The clips ordered by start.
+    pub fn clips(&self) -> &[Clip] {
+        &self.clips
+    }
+
+    pub fn clip(&self, id: KeyId) -> Option<&Clip> {
+        self.clips.iter().find(|c| c.id == id)
+    }
+
+    /// The clip playing at `time`.
+    pub fn clip_at(&self, time: Time) -> Option<&Clip> {
+        self.clips.iter().find(|c| c.contains(time))
+    }
+
+    /// Adds a clip, `None` if it is empty or overlaps another clip.
+    pub fn insert_clip(
+        &mut self,
+        start: Time,
+        length: Time,
+        label: impl Into<String>,
+        color: [f32; 4],
+    ) -> Option<KeyId> {
+        if length <= Time::ZERO || !self.is_free(start, start + length, None) {
+            return None;
+        }
+        let id = KeyId(self.next_clip_id);
+        self.next_clip_id += 1;
+        self.insert_sorted(Clip {
+            id,
+            start,
+            length,
+            label: label.into(),
+            color,
+        });
+        Some(id)
+    }
+
+    pub fn remove_clip(&mut self, id: KeyId) -> Option<Clip> {
+        let index = self.clips.iter().position(|c| c.id == id)?;
+        Some(self.clips.remove(index))
+    }
+
+    /// Moves the clip to start at `start`, `false` if it would overlap another clip.
+    pub fn move_clip(&mut self, id: KeyId, start: Time) -> bool {
+        let Some(length) = self.clip(id).map(|c| c.length) else {
+            return false;
+        };
+        self.trim_clip(id, start, start + length)
+    }
+
+    /// Changes the start and end of the clip, `false` if the clip would be empty or overlap
+    /// another clip.
+    pub fn trim_clip(&mut self, id: KeyId, start: Time, end: Time) -> bool {
+        if end <= start || !self.is_free(start, end, Some(id)) {
+            return false;
+        }
+        let Some(mut clip) = self.remove_clip(id) else {
+            return false;
+        };
+        clip.start = start;
+        clip.length = end - start;
+        self.insert_sorted(clip);
+        true
+    }
+
+    /// Cuts the clip in two at `time`, the second part keeps the label and color and gets a
+    /// new id. `None` unless `time` lies strictly inside the clip.
+    pub fn split_clip(&mut self, id: KeyId, time: Time) -> Option<KeyId> {
+        let clip = self.clips.iter_mut().find(|c| c.id == id)?;
+        if time <= clip.start || time >= clip.end() {
+            return None;
+        }
+        let end = clip.end();
+        clip.length = time - clip.start;
+        let second = Clip {
+            id: KeyId(self.next_clip_id),
+            start: time,
+            length: end - time,
+            label: clip.label.clone(),
+            color: clip.color,
+        };
+        self.next_clip_id += 1;
+        let id = second.id;
+        self.insert_sorted(second);
+        Some(id)
+    }
+
+    pub fn set_clip_label(&mut self, id: KeyId, label: impl Into<String>) -> bool {
+        match self.clips.iter_mut().find(|c| c.id == id) {
+            Some(clip) => {
+                clip.label = label.into();
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn set_clip_color(&mut self, id: KeyId, color: [f32; 4]) -> bool {
+        match self.clips.iter_mut().find(|c| c.id == id) {
+            Some(clip) => {
+                clip.color = color;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Whether no clip other than `ignore` overlaps `start..end`.
+    fn is_free(&self, start: Time, end: Time, ignore: Option<KeyId>) -> bool {
+        self.clips
+            .iter()
+            .filter(|c| Some(c.id) != ignore)
+            .all(|c| c.end() <= start || c.start >= end)
+    }
+
+    fn insert_sorted(&mut self, clip: Clip) {
+        let index = self.clips.partition_point(|c| c.start <= clip.start);
+        self.clips.insert(index, clip);
+    }
+}
+
+/// Saved form of a [`ClipTrack`], clip ids are not kept.
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+struct ClipTrackData {
+    name: String,
+    default_length: Time,
+    default_color: [f32; 4],
+    clips: Vec<ClipData>,
+}
+
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+struct ClipData {
+    start: Time,
+    length: Time,
+    label: String,
+    color: [f32; 4],
+}
+
+#[cfg(feature = "serde")]
+impl ClipTrack {
+    /// Recreates a track from [`Track::save`] data, `None` if the data is invalid or clips
+    /// overlap.
+    pub fn load(data: TrackData) -> Option<Self> {
+        let data: ClipTrackData = data.deserialize_into().ok()?;
+        let mut track = Self::new(data.name).with_defaults(data.default_length, data.default_color);
+        for clip in data.clips {
+            track.insert_clip(clip.start, clip.length, clip.label, clip.color)?;
+        }
+        Some(track)
+    }
+}
+
+impl Track for ClipTrack {
+    fn draw_head(&mut self, ui: &imgui::Ui) {
+        draw_track_name(ui, &self.name, Self::TRACK_HEIGHT);
+    }
+
+    fn head_config(&mut self) -> (String, f32) {
+        (format!("clip_track_head_{}", self.name), Self::TRACK_HEIGHT)
+    }
+
+    fn draw_lane(&mut self, ui: &imgui::Ui, view: &TrackView) {
+        let top = view.lane_min.y + 3.0;
+        let bottom = view.lane_max.y - 3.0;
+        let text_y = (top + bottom - ui.text_line_height()) / 2.0;
+
+        for clip in &self.clips {
+            if clip.end() < view.left_time || clip.start > view.right_time {
+                continue;
+            }
+            let min = [view.time_to_x(clip.start), top];
+            let max = [view.time_to_x(clip.end()), bottom];
+            view.draw_list
+                .add_rect(min, max, clip.color)
+                .filled(true)
+                .rounding(Self::ROUNDING)
+                .build();
+            if view.is_selected(clip.id) {
+                view.draw_list
+                    .add_rect(min, max, [1.0, 0.85, 0.6])
+                    .rounding(Self::ROUNDING)
+                    .thickness(2.0)
+                    .build();
+            }
+            view.draw_list.with_clip_rect_intersect(
+                [min[0] + 4.0, min[1]],
+                [max[0] - 4.0, max[1]],
+                || {
+                    view.draw_list
+                        .add_text([min[0] + 6.0, text_y], [1.0; 3], &clip.label);
+                },
+            );
+        }
+    }
+
+    fn key_times(&self) -> Vec<(KeyId, Time)> {
+        self.clips.iter().map(|c| (c.id, c.start)).collect()
+    }
+
+    /// Fills the gap at `time` with a clip of the default length, shortened to fit.
+    fn add_key_at(&mut self, time: Time) -> Option<KeyId> {
+        if self.clip_at(time).is_some() {
+            return None;
+        }
+        let next = self
+            .clips
+            .iter()
+            .map(|c| c.start)
+            .filter(|start| *start > time)
+            .min();
+        let end = next.map_or(time + self.default_length, |next| {
+            next.min(time + self.default_length)
+        });
+        let label = format!("Clip {}", self.next_clip_id + 1);
+        self.insert_clip(time, end - time, label, self.default_color)
+    }
+
+    fn move_key(&mut self, key: KeyId, time: Time) -> bool {
+        self.move_clip(key, time)
+    }
+
+    fn delete_key(&mut self, key: KeyId) -> bool {
+        self.remove_clip(key).is_some()
+    }
+
+    fn key_end(&self, key: KeyId) -> Option<Time> {
+        self.clip(key).map(Clip::end)
+    }
+
+    fn set_key_range(&mut self, key: KeyId, start: Time, end: Time) -> bool {
+        self.trim_clip(key, start, end)
+    }
+
+    fn split_key(&mut self, key: KeyId, time: Time) -> Option<KeyId> {
+        self.split_clip(key, time)
+    }
+
+    fn clipboard_type(&self) -> Option<&str> {
+        Some(Self::TYPE_TAG)
+    }
+
+    /// The length in ticks, the color and the escaped label, tab separated.
+    fn copy_key(&self, key: KeyId) -> Option<String> {
+        let clip = self.clip(key)?;
+        let [r, g, b, a] = clip.color;
+        Some(format!(
+            "{}\t{} {} {} {}\t{}",
+            clip.length.ticks(),
+            r,
+            g,
+            b,
+            a,
+            escape_field(&clip.label)
+        ))
+    }
+
+    fn paste_key(&mut self, time: Time, text: &str) -> Result<KeyId, PasteError> {
+        let (length, color, label) =
+            parse_clip_text(text).ok_or_else(|| PasteError::InvalidKey(text.to_string()))?;
+        self.insert_clip(time, length, label, color)
+            .ok_or(PasteError::Overlap(time))
+    }
+
+    fn key_state(&self, key: KeyId) -> Option<KeyState> {
+        Some(Rc::new(self.clip(key)?.clone()))
+    }
+
+    fn restore_key(&mut self, state: &dyn Any) -> bool {
+        let Some(clip) = state.downcast_ref::<Clip>() else {
+            return false;
+        };
+        self.remove_clip(clip.id);
+        self.next_clip_id = self.next_clip_id.max(clip.id.0 + 1);
+        self.insert_sorted(clip.clone());
+        true
+    }
+
+    fn name(&self) -> Option<&str> {
+        Some(&self.name)
+    }
+
+    #[cfg(feature = "serde")]
+    fn type_tag(&self) -> Option<String> {
+        Some(Self::TYPE_TAG.to_string())
+    }
+
+    #[cfg(feature = "serde")]
+    fn save(&self) -> Option<TrackData> {
+        let clips = self
+            .clips
+            .iter()
+            .map(|clip| ClipData {
+                start: clip.start,
+                length: clip.length,
+                label: clip.label.clone(),
+                color: clip.color,
+            })
+            .collect();
+        serde_value::to_value(ClipTrackData {
+            name: self.name.clone(),
+            default_length: self.default_length,
+            default_color: self.default_color,
+            clips,
+        })
+        .ok()
+    }
+}
+
+/// Length, color and label of [`ClipTrack::copy_key`] text.
+fn parse_clip_text(text: &str) -> Option<(Time, [f32; 4], String)> {
+    let mut fields = text.splitn(3, '\t');
+    let length = Time::from_ticks(fields.next()?.trim().parse().ok()?);
+    let mut components = fields.next()?.split_whitespace();
+    let mut color = [0.0; 4];
+    for component in &mut color {
+        *component = components.next()?.parse().ok()?;
+    }
+    let label = unescape_field(fields.next()?)?;
+    (length > Time::ZERO).then_some((length, color, label))
+}
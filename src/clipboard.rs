@@ -0,0 +1,382 @@
+use std::{error::Error, fmt};
+
+use crate::{KeyRef, KeyState, Time, Timeline, TrackId};
+
+/// First line of copied keys, the number is the format version.
+const CLIPBOARD_HEADER: &str = "imgui_timeline_rs keys 1";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PasteError {
+    /// The text is not keys copied from a timeline.
+    NotKeys,
+    /// A copied key could not be read or created on its track.
+    InvalidKey(String),
+    /// Keys of one type can't be pasted onto a track of another.
+    IncompatibleTrack { expected: String, found: String },
+    /// The target track is not in the timeline.
+    UnknownTrack(TrackId),
+    /// Keys were copied from more tracks than there are below the target track.
+    NotEnoughTracks,
+    /// A key pasted at this time would overlap a key of its track, like a clip pasted onto
+    /// another clip.
+    Overlap(Time),
+}
+
+impl fmt::Display for PasteError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotKeys => write!(f, "clipboard does not contain keys"),
+            Self::InvalidKey(line) => write!(f, "invalid key '{}'", line),
+            Self::IncompatibleTrack { expected, found } => {
+                write!(f, "can't paste {} keys onto a {} track", expected, found)
+            }
+            Self::UnknownTrack(track) => write!(f, "no track with id {}", track.0),
+            Self::NotEnoughTracks => write!(f, "keys were copied from more tracks than available"),
+            Self::Overlap(time) => write!(f, "key pasted at {} overlaps another key", time),
+        }
+    }
+}
+
+impl Error for PasteError {}
+
+/// Keys of one source track in clipboard text.
+struct CopiedTrack<'a> {
+    value_type: &'a str,
+    /// Offset from the earliest copied key in ticks and the key's text.
+    keys: Vec<(i64, &'a str)>,
+}
+
+impl Timeline {
+    /// The selected keys as clipboard text, with their times relative to the earliest one.
+    /// `None` if no selected key can be copied.
+    pub fn copy_selection(&self) -> Option<String> {
+        self.copy_keys().map(|(text, _)| text)
+    }
+
+    /// Copies the selected keys that can be copied and deletes them as one undo step, keys
+    /// that can't be copied stay selected.
+    pub fn cut_selection(&mut self) -> Option<String> {
+        self.cut_keys().map(|(text, _)| text)
+    }
+
+    /// Like [`Timeline::cut_selection`], also returning the deleted keys.
+    pub(crate) fn cut_keys(&mut self) -> Option<(String, Vec<KeyRef>)> {
+        let (text, copied) = self.copy_keys()?;
+        self.begin_group();
+        let deleted = copied
+            .into_iter()
+            .filter(|key| self.delete_key(*key))
+            .collect();
+        self.end_group();
+        Some((text, deleted))
+    }
+
+    /// The clipboard text of the selection and the keys written into it.
+    fn copy_keys(&self) -> Option<(String, Vec<KeyRef>)> {
+        let mut tracks = Vec::new();
+        let mut copied = Vec::new();
+        for entry in &self.tracks {
+            let Some(value_type) = entry.track.clipboard_type() else {
+                continue;
+            };
+            let keys = entry
+                .track
+                .key_times()
+                .into_iter()
+                .filter(|(key, _)| self.is_key_selected(KeyRef::new(entry.id, *key)))
+                .filter_map(|(key, time)| {
+                    let text = entry.track.copy_key(key)?;
+                    copied.push(KeyRef::new(entry.id, key));
+                    Some((time, text))
+                })
+                .collect::<Vec<_>>();
+            if !keys.is_empty() {
+                tracks.push((value_type, keys));
+            }
+        }
+        let start = tracks
+            .iter()
+            .flat_map(|(_, keys)| keys.iter().map(|(time, _)| *time))
+            .min()?;
+
+        let mut text = String::from(CLIPBOARD_HEADER);
+        for (value_type, keys) in tracks {
+            text += &format!("\ntrack\t{}", value_type);
+            for (time, key) in keys {
+                text += &format!("\n{}\t{}", (time - start).ticks(), key);
+            }
+        }
+        Some((text, copied))
+    }
+
+    /// Pastes copied keys with their first key at `time`. Keys of the first copied track go
+    /// onto `track`, those of further copied tracks onto the tracks below it. The pasted keys
+    /// become the selection, nothing is pasted if any track is incompatible or any key can't
+    /// be created.
+    pub fn paste(
+        &mut self,
+        text: &str,
+        track: TrackId,
+        time: Time,
+    ) -> Result<Vec<KeyRef>, PasteError> {
+        let copied = parse_clipboard(text)?;
+        let first = self
+            .track_index(track)
+            .ok_or(PasteError::UnknownTrack(track))?;
+        let targets = self
+            .tracks
+            .get(first..first + copied.len())
+            .filter(|targets| targets.len() == copied.len())
+            .ok_or(PasteError::NotEnoughTracks)?;
+        for (target, copied) in targets.iter().zip(&copied) {
+            let found = target.track.clipboard_type();
+            if found != Some(copied.value_type) {
+                return Err(PasteError::IncompatibleTrack {
+                    expected: copied.value_type.to_string(),
+                    found: found.unwrap_or("non-keyframe").to_string(),
+                });
+            }
+        }
+        let targets = targets.iter().map(|e| e.id).collect::<Vec<_>>();
+
+        // Keys are recorded once all of them are created, so a failed paste leaves no trace.
+        let mut pasted = Vec::new();
+        for (target, copied) in targets.into_iter().zip(copied) {
+            for (offset, text) in copied.keys {
+                let time = time + Time::from_ticks(offset);
+                let existing = self.key_state_at(target, time);
+                let Some(track) = self.track_mut(target) else {
+                    continue;
+                };
+                match track.paste_key(time, text) {
+                    Ok(key) => {
+                        let replaced = existing.filter(|(k, _)| *k == key).map(|(_, s)| s);
+                        pasted.push((KeyRef::new(target, key), replaced));
+                    }
+                    Err(error) => {
+                        self.take_back_pasted(pasted);
+                        return Err(error);
+                    }
+                }
+            }
+        }
+
+        self.begin_group();
+        for (key, replaced) in &pasted {
+            self.record_add_key(*key, replaced.clone());
+        }
+        self.end_group();
+        self.selection = pasted.into_iter().map(|(key, _)| key).collect();
+        Ok(self.selection.clone())
+    }
+
+    /// Deletes pasted keys again, restoring the keys they replaced.
+    fn take_back_pasted(&mut self, pasted: Vec<(KeyRef, Option<KeyState>)>) {
+        for (key, replaced) in pasted.into_iter().rev() {
+            let Some(track) = self.track_mut(key.track) else {
+                continue;
+            };
+            match replaced {
+                Some(state) => track.restore_key(state.as_ref()),
+                None => track.delete_key(key.key),
+            };
+        }
+    }
+}
+
+/// Escapes line breaks, tabs and backslashes so free text fits in a field of a key line.
+pub(crate) fn escape_field(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Reverses [`escape_field`], `None` on an unknown escape.
+pub(crate) fn unescape_field(text: &str) -> Option<String> {
+    let mut unescaped = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        unescaped.push(match chars.next()? {
+            '\\' => '\\',
+            'n' => '\n',
+            'r' => '\r',
+            't' => '\t',
+            _ => return None,
+        });
+    }
+    Some(unescaped)
+}
+
+fn parse_clipboard(text: &str) -> Result<Vec<CopiedTrack<'_>>, PasteError> {
+    let mut lines = text.lines();
+    if lines.next().map(str::trim_end) != Some(CLIPBOARD_HEADER) {
+        return Err(PasteError::NotKeys);
+    }
+    let mut tracks: Vec<CopiedTrack> = Vec::new();
+    for line in lines.filter(|l| !l.trim().is_empty()) {
+        let (first, rest) = line
+            .split_once('\t')
+            .ok_or_else(|| PasteError::InvalidKey(line.to_string()))?;
+        if first == "track" {
+            tracks.push(CopiedTrack {
+                value_type: rest,
+                keys: Vec::new(),
+            });
+            continue;
+        }
+        let offset = first
+            .parse()
+            .map_err(|_| PasteError::InvalidKey(line.to_string()))?;
+        tracks
+            .last_mut()
+            .ok_or(PasteError::NotKeys)?
+            .keys
+            .push((offset, rest));
+    }
+    if tracks.is_empty() {
+        return Err(PasteError::NotKeys);
+    }
+    Ok(tracks)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PasteError;
+    use crate::{ClipTrack, EventTrack, KeyFrameTrack, KeyRef, KeyValue, Time, Timeline};
+
+    /// A value without clipboard text.
+    #[derive(Clone)]
+    struct Opaque;
+
+    impl KeyValue for Opaque {
+        fn lerp(&self, _other: &Self, _t: f32) -> Self {
+            Self
+        }
+
+        fn zero() -> Self {
+            Self
+        }
+
+        fn type_name() -> &'static str {
+            "opaque"
+        }
+    }
+
+    #[test]
+    fn cut_keeps_keys_that_cannot_be_copied() {
+        let mut timeline = Timeline::new("test");
+        let mut floats = KeyFrameTrack::<f32>::new("floats");
+        let float = floats.insert_key(Time::ZERO, 1.0);
+        let mut opaque = KeyFrameTrack::<Opaque>::new("opaque");
+        let other = opaque.insert_key(Time::ZERO, Opaque);
+        let floats = timeline.add_track(floats);
+        let opaque = timeline.add_track(opaque);
+        let (float, other) = (KeyRef::new(floats, float), KeyRef::new(opaque, other));
+        timeline.set_selection([float, other]);
+
+        let (text, deleted) = timeline.cut_keys().unwrap();
+        assert_eq!(deleted, [float]);
+        assert_eq!(timeline.selection(), [other]);
+        assert_eq!(timeline.track(opaque).unwrap().key_times().len(), 1);
+
+        let pasted = timeline.paste(&text, floats, Time::ZERO).unwrap();
+        assert_eq!(pasted.len(), 1);
+    }
+
+    #[test]
+    fn paste_keeps_key_offsets() {
+        let mut timeline = Timeline::new("test");
+        let mut floats = KeyFrameTrack::<f32>::new("floats");
+        let first = floats.insert_key(Time::from_millis(500), 1.0);
+        let second = floats.insert_key(Time::from_millis(1500), 2.0);
+        let track = timeline.add_track(floats);
+        timeline.set_selection([KeyRef::new(track, first), KeyRef::new(track, second)]);
+
+        let text = timeline.copy_selection().unwrap();
+        timeline
+            .paste(&text, track, Time::from_millis(3000))
+            .unwrap();
+        let times = timeline
+            .track(track)
+            .unwrap()
+            .key_times()
+            .into_iter()
+            .map(|(_, time)| time)
+            .collect::<Vec<_>>();
+        assert_eq!(times, [500, 1500, 3000, 4000].map(Time::from_millis));
+
+        timeline.remove_track(track);
+        assert_eq!(
+            timeline.paste(&text, track, Time::ZERO),
+            Err(PasteError::UnknownTrack(track))
+        );
+    }
+
+    #[test]
+    fn failed_paste_creates_no_keys() {
+        let mut timeline = Timeline::new("test");
+        let mut clips = ClipTrack::new("clips");
+        let first = clips.insert_clip(Time::ZERO, Time::from_millis(1000), "a", [1.0; 4]);
+        let second = clips.insert_clip(
+            Time::from_millis(3000),
+            Time::from_millis(1000),
+            "b",
+            [1.0; 4],
+        );
+        let track = timeline.add_track(clips);
+        timeline.set_selection([first, second].map(|key| KeyRef::new(track, key.unwrap())));
+        let text = timeline.copy_selection().unwrap();
+
+        // The first clip fits at -2.5 s, the second would overlap the clip at 0 s.
+        let error = timeline.paste(&text, track, Time::from_millis(-2500));
+        assert_eq!(error, Err(PasteError::Overlap(Time::from_millis(500))));
+        assert_eq!(timeline.track(track).unwrap().key_times().len(), 2);
+        assert!(!timeline.can_undo());
+    }
+
+    #[test]
+    fn clip_labels_keep_line_breaks_and_tabs() {
+        let label = "two\nlines\twith a \\ backslash";
+        let mut timeline = Timeline::new("test");
+        let mut clips = ClipTrack::new("clips");
+        let clip = clips.insert_clip(Time::ZERO, Time::from_millis(1000), label, [1.0; 4]);
+        let track = timeline.add_track(clips);
+        timeline.set_selection([KeyRef::new(track, clip.unwrap())]);
+
+        let text = timeline.copy_selection().unwrap();
+        let pasted = timeline
+            .paste(&text, track, Time::from_millis(2000))
+            .unwrap();
+        let clips = timeline.track_as::<ClipTrack>(track).unwrap();
+        assert_eq!(clips.clip(pasted[0].key).unwrap().label(), label);
+    }
+
+    #[test]
+    fn event_payloads_keep_line_breaks() {
+        let payload = "play(\"a\")\nplay(\"b\")";
+        let mut timeline = Timeline::new("test");
+        let mut events = EventTrack::new("events");
+        let event = events.insert_event(Time::ZERO, payload);
+        let track = timeline.add_track(events);
+        timeline.set_selection([KeyRef::new(track, event)]);
+
+        let text = timeline.copy_selection().unwrap();
+        let pasted = timeline
+            .paste(&text, track, Time::from_millis(2000))
+            .unwrap();
+        let events = timeline.track_as::<EventTrack>(track).unwrap();
+        assert_eq!(events.event(pasted[0].key).unwrap().payload(), payload);
+    }
+}
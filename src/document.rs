@@ -0,0 +1,262 @@
+use std::{error::Error, fmt};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    view::{MAX_TIME_SCALE, MIN_TIME_SCALE},
+    EditorMode, FrameRate, Marker, PlaybackMode, SnapSettings, Time, TimeFormat, Timeline,
+    TrackEntry, TrackId, TrackRegistry,
+};
+
+/// Version written by [`Timeline::save_document`]. Fields added in later versions get
+/// defaults, so documents of any version up to this one load.
+pub const DOCUMENT_VERSION: u32 = 1;
+
+/// Content of a track in a saved document, independent of the file format.
+pub type TrackData = serde_value::Value;
+
+/// Converts a serializable track or value for [`crate::Track::save`].
+pub fn to_track_data<T: Serialize>(value: &T) -> Option<TrackData> {
+    serde_value::to_value(value).ok()
+}
+
+/// The edited content of a timeline, saved with the host's scene. View state like zoom and
+/// scroll is kept separately in [`ViewState`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TimelineDocument {
+    pub version: u32,
+    pub duration: Time,
+    pub frame_rate: FrameRate,
+    pub playback_mode: PlaybackMode,
+    pub in_point: Option<Time>,
+    pub out_point: Option<Time>,
+    pub markers: Vec<Marker>,
+    pub tracks: Vec<TrackDocument>,
+}
+
+impl Default for TimelineDocument {
+    fn default() -> Self {
+        Self {
+            version: DOCUMENT_VERSION,
+            duration: Time::from_millis(30_000),
+            frame_rate: FrameRate::default(),
+            playback_mode: PlaybackMode::default(),
+            in_point: None,
+            out_point: None,
+            markers: Vec::new(),
+            tracks: Vec::new(),
+        }
+    }
+}
+
+/// A saved track, `kind` is its [`crate::Track::type_tag`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TrackDocument {
+    pub kind: String,
+    pub data: TrackData,
+}
+
+/// How the timeline is looked at, worth keeping per user rather than per document.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ViewState {
+    /// Playhead position.
+    pub time: Time,
+    /// Time at the left edge of the lanes.
+    pub left_time: Time,
+    pub time_scale: f32,
+    pub track_scroll_y: f32,
+    pub time_format: TimeFormat,
+    pub snap: SnapSettings,
+    pub editor_mode: EditorMode,
+}
+
+impl Default for ViewState {
+    fn default() -> Self {
+        Self {
+            time: Time::ZERO,
+            left_time: Time::from_millis(2000),
+            time_scale: 0.4,
+            track_scroll_y: 0.0,
+            time_format: TimeFormat::default(),
+            snap: SnapSettings::default(),
+            editor_mode: EditorMode::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DocumentError {
+    /// The document was written by a newer version of the timeline.
+    UnsupportedVersion(u32),
+    /// No loader is known for the track type.
+    UnknownTrackType(String),
+    /// The saved data of a track of this type could not be read.
+    InvalidTrack(String),
+}
+
+impl fmt::Display for DocumentError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnsupportedVersion(version) => {
+                write!(f, "unsupported document version {}", version)
+            }
+            Self::UnknownTrackType(kind) => write!(f, "unknown track type '{}'", kind),
+            Self::InvalidTrack(kind) => write!(f, "invalid '{}' track", kind),
+        }
+    }
+}
+
+impl Error for DocumentError {}
+
+impl Timeline {
+    /// The tracks, keys, markers and playback settings of the timeline. Tracks without a
+    /// [`crate::Track::type_tag`] or whose content can't be saved are left out.
+    pub fn save_document(&self) -> TimelineDocument {
+        let tracks = self
+            .tracks
+            .iter()
+            .filter_map(|entry| {
+                Some(TrackDocument {
+                    kind: entry.track.type_tag()?,
+                    data: entry.track.save()?,
+                })
+            })
+            .collect();
+        TimelineDocument {
+            version: DOCUMENT_VERSION,
+            duration: self.duration,
+            frame_rate: self.frame_rate,
+            playback_mode: self.playback_mode,
+            in_point: self.in_point,
+            out_point: self.out_point,
+            markers: self.markers.clone(),
+            tracks,
+        }
+    }
+
+    /// Replaces the content of the timeline with a saved document that only contains
+    /// built-in track types, see [`Timeline::load_document_with`].
+    pub fn load_document(&mut self, document: TimelineDocument) -> Result<(), DocumentError> {
+        self.load_document_with(document, &TrackRegistry::default())
+    }
+
+    /// Replaces the content of the timeline with a saved document, creating its tracks
+    /// through `registry`. On error the timeline is left unchanged. Tracks get new ids and
+    /// the undo history is cleared.
+    pub fn load_document_with(
+        &mut self,
+        document: TimelineDocument,
+        registry: &TrackRegistry,
+    ) -> Result<(), DocumentError> {
+        if document.version > DOCUMENT_VERSION {
+            return Err(DocumentError::UnsupportedVersion(document.version));
+        }
+        let tracks = document
+            .tracks
+            .into_iter()
+            .map(|saved| registry.load(&saved.kind, saved.data))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        self.end_drags();
+        self.track_drag = None;
+        self.last_tick_time = None;
+        self.tracks.clear();
+        self.selection.clear();
+        for track in tracks {
+            let id = TrackId(self.next_track_id);
+            self.next_track_id += 1;
+            self.tracks.push(TrackEntry { id, track });
+        }
+        self.duration = document.duration;
+        self.frame_rate = document.frame_rate;
+        self.playback_mode = document.playback_mode;
+        self.in_point = document.in_point;
+        self.out_point = document.out_point;
+        self.markers = document.markers;
+        self.clear_history();
+        Ok(())
+    }
+
+    pub fn view_state(&self) -> ViewState {
+        ViewState {
+            time: self.time,
+            left_time: self.left_time,
+            time_scale: self.time_scale,
+            track_scroll_y: self.track_scroll_y,
+            time_format: self.time_format,
+            snap: self.snap,
+            editor_mode: self.editor_mode,
+        }
+    }
+
+    /// Restores zoom, scroll and playhead, the vertical scroll applies on the next draw.
+    pub fn set_view_state(&mut self, state: ViewState) {
+        self.time = state.time;
+        self.left_time = state.left_time;
+        self.time_scale = state.time_scale.clamp(MIN_TIME_SCALE, MAX_TIME_SCALE);
+        self.pending_scroll_y = Some(state.track_scroll_y.max(0.0));
+        self.time_format = state.time_format;
+        self.snap = state.snap;
+        self.set_editor_mode(state.editor_mode);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ClipTrack, EventTrack, KeyFrameTrack};
+
+    fn round_trip(document: &TimelineDocument) -> TimelineDocument {
+        serde_value::to_value(document)
+            .unwrap()
+            .deserialize_into()
+            .unwrap()
+    }
+
+    #[test]
+    fn built_in_tracks_round_trip() {
+        let mut timeline = Timeline::new("test");
+        let mut keys = KeyFrameTrack::new("Opacity");
+        keys.insert_key(Time::from_millis(0), 0.0f32);
+        keys.insert_key(Time::from_millis(500), 1.0f32);
+        timeline.add_track(keys);
+        let mut clips = ClipTrack::new("Shots");
+        clips.insert_clip(
+            Time::from_millis(100),
+            Time::from_millis(900),
+            "Wide\tshot",
+            [0.2, 0.4, 0.8, 1.0],
+        );
+        timeline.add_track(clips);
+        let mut events = EventTrack::new("Cues").with_default_payload("beep");
+        events.insert_event(Time::from_millis(250), "play \"door\"");
+        timeline.add_track(events);
+        timeline.set_playback_range(Time::from_millis(100), Time::from_millis(800));
+
+        let saved = timeline.save_document();
+        assert_eq!(saved.tracks.len(), 3);
+        let mut loaded = Timeline::new("loaded");
+        loaded.load_document(round_trip(&saved)).unwrap();
+        assert_eq!(loaded.save_document(), saved);
+        let times = loaded
+            .tracks
+            .iter()
+            .map(|entry| entry.track.key_times().len())
+            .collect::<Vec<_>>();
+        assert_eq!(times, [2, 1, 1]);
+    }
+
+    #[test]
+    fn loading_ends_an_open_edit_group() {
+        let mut timeline = Timeline::new("test");
+        timeline.begin_group();
+        timeline.load_document(TimelineDocument::default()).unwrap();
+        let track = timeline.add_track(EventTrack::new("Cues"));
+        timeline.add_key(track, Time::from_millis(100));
+        assert!(timeline.can_undo());
+        assert!(timeline.undo());
+        assert!(timeline.track(track).unwrap().key_times().is_empty());
+    }
+}
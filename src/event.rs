@@ -0,0 +1,180 @@
+use crate::{KeyRef, PasteError, Time, Timeline, TrackId};
+
+/// Something the user did in the timeline, see [`Response`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum TimelineEvent {
+    /// The user grabbed the ruler or playhead.
+    ScrubStarted,
+    ScrubEnded,
+    /// The playhead moved, by scrubbing, typing a time or a transport button.
+    TimeChanged(Time),
+    PlaybackStarted,
+    PlaybackStopped,
+    KeyAdded(KeyRef),
+    /// Sent for every frame a dragged key changes time.
+    KeyMoved {
+        key: KeyRef,
+        from: Time,
+        to: Time,
+    },
+    KeyDeleted(KeyRef),
+    /// The value or interpolation of a key changed, by undo or redo.
+    KeyChanged(KeyRef),
+    SelectionChanged,
+    /// A track came back by undo or redo.
+    TrackAdded(TrackId),
+    /// A track was taken out by undo or redo.
+    TrackRemoved(TrackId),
+    TrackMoved {
+        track: TrackId,
+        from: usize,
+        to: usize,
+    },
+    PlaybackRangeChanged,
+    /// Ctrl+V with text that isn't keys or doesn't fit the tracks.
+    PasteRejected(PasteError),
+}
+
+/// What the user did during a [`Timeline::draw`], including undo and redo. Undo and redo
+/// made between draws, e.g. by an [`crate::UndoHost`], are reported by the next draw, other
+/// changes made through methods are not.
+#[derive(Debug, Clone, Default)]
+pub struct Response {
+    events: Vec<TimelineEvent>,
+}
+
+impl Response {
+    /// The events in the order they happened.
+    pub fn events(&self) -> &[TimelineEvent] {
+        &self.events
+    }
+
+    pub fn into_events(self) -> Vec<TimelineEvent> {
+        self.events
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    /// The new playhead time if it was moved.
+    pub fn time_changed(&self) -> Option<Time> {
+        self.events.iter().rev().find_map(|e| match e {
+            TimelineEvent::TimeChanged(time) => Some(*time),
+            _ => None,
+        })
+    }
+
+    /// Whether keys were added, moved or deleted.
+    pub fn keys_changed(&self) -> bool {
+        self.events.iter().any(|e| {
+            matches!(
+                e,
+                TimelineEvent::KeyAdded(_)
+                    | TimelineEvent::KeyMoved { .. }
+                    | TimelineEvent::KeyDeleted(_)
+                    | TimelineEvent::KeyChanged(_)
+            )
+        })
+    }
+
+    pub fn selection_changed(&self) -> bool {
+        self.events.contains(&TimelineEvent::SelectionChanged)
+    }
+}
+
+/// State compared before and after drawing to report changes.
+pub(crate) struct ObservedState {
+    time: Time,
+    playing: bool,
+    selection: Vec<KeyRef>,
+    range: (Option<Time>, Option<Time>),
+}
+
+impl Timeline {
+    /// Whether the playhead is being dragged, playback pauses meanwhile.
+    pub fn is_scrubbing(&self) -> bool {
+        self.scrubbing
+    }
+
+    pub(crate) fn emit(&mut self, event: TimelineEvent) {
+        self.events.push(event);
+    }
+
+    pub(crate) fn observe(&self) -> ObservedState {
+        ObservedState {
+            time: self.time,
+            playing: self.playing,
+            selection: self.selection.clone(),
+            range: (self.in_point, self.out_point),
+        }
+    }
+
+    /// Turns the events of this frame into a response, adding the state changes found by
+    /// comparing with the state before drawing.
+    pub(crate) fn finish_response(&mut self, before: ObservedState) -> Response {
+        if self.time != before.time {
+            self.emit(TimelineEvent::TimeChanged(self.time));
+        }
+        if self.playing != before.playing {
+            self.emit(if self.playing {
+                TimelineEvent::PlaybackStarted
+            } else {
+                TimelineEvent::PlaybackStopped
+            });
+        }
+        if self.selection != before.selection {
+            self.emit(TimelineEvent::SelectionChanged);
+        }
+        if (self.in_point, self.out_point) != before.range {
+            self.emit(TimelineEvent::PlaybackRangeChanged);
+        }
+        Response {
+            events: std::mem::take(&mut self.events),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::EventTrack;
+
+    #[test]
+    fn response_reports_changed_state() {
+        let mut timeline = Timeline::new("test");
+        let track = timeline.add_track(EventTrack::new("events"));
+        let before = timeline.observe();
+        assert!(timeline.finish_response(before).is_empty());
+
+        let before = timeline.observe();
+        let key = timeline.add_key(track, Time::ZERO).unwrap();
+        timeline.emit(TimelineEvent::KeyAdded(key));
+        timeline.set_selection([key]);
+        timeline.set_time(Time::from_millis(500));
+        timeline.set_time(Time::from_millis(1500));
+        timeline.set_playing(true);
+        timeline.set_in_point(Some(Time::from_millis(1000)));
+        let response = timeline.finish_response(before);
+        assert_eq!(
+            response.events(),
+            [
+                TimelineEvent::KeyAdded(key),
+                TimelineEvent::TimeChanged(Time::from_millis(1500)),
+                TimelineEvent::PlaybackStarted,
+                TimelineEvent::SelectionChanged,
+                TimelineEvent::PlaybackRangeChanged,
+            ]
+        );
+        assert_eq!(response.time_changed(), Some(Time::from_millis(1500)));
+        assert!(response.keys_changed());
+        assert!(response.selection_changed());
+
+        let before = timeline.observe();
+        timeline.pause();
+        let response = timeline.finish_response(before);
+        assert_eq!(response.events(), [TimelineEvent::PlaybackStopped]);
+        assert_eq!(response.time_changed(), None);
+        assert!(!response.keys_changed());
+    }
+}
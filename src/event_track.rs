@@ -0,0 +1,284 @@
+use std::{any::Any, rc::Rc};
+
+#[cfg(feature = "serde")]
+use crate::TrackData;
+use crate::{
+    clipboard::{escape_field, unescape_field},
+    view::draw_track_name,
+    KeyId, KeyState, PasteError, Time, Track, TrackView,
+};
+
+/// An instant on an [`EventTrack`], reported by [`crate::Timeline::tick`] when playback
+/// crosses it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TrackEvent {
+    id: KeyId,
+    time: Time,
+    payload: String,
+}
+
+impl TrackEvent {
+    pub fn id(&self) -> KeyId {
+        self.id
+    }
+
+    pub fn time(&self) -> Time {
+        self.time
+    }
+
+    /// What the host does when the event fires, e.g. the name of a sound or a script call.
+    pub fn payload(&self) -> &str {
+        &self.payload
+    }
+}
+
+/// A track of instantaneous events with a payload, drawn as flags labeled with the payload.
+pub struct EventTrack {
+    name: String,
+    events: Vec<TrackEvent>,
+    next_event_id: u64,
+    default_payload: String,
+}
+
+impl EventTrack {
+    const TRACK_HEIGHT: f32 = 24.0;
+    const FLAG_SIZE: f32 = 6.0;
+    /// Tag of event tracks in saved documents.
+    pub const TYPE_TAG: &'static str = "event";
+
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            events: Vec::new(),
+            next_event_id: 0,
+            default_payload: String::new(),
+        }
+    }
+
+    /// Payload of events created by double-clicking the lane.
+    pub fn with_default_payload(mut self, payload: impl Into<String>) -> Self {
+        self.default_payload = payload.into();
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The events ordered by time.
+    pub fn events(&self) -> &[TrackEvent] {
+        &self.events
+    }
+
+    pub fn event(&self, id: KeyId) -> Option<&TrackEvent> {
+        self.events.iter().find(|e| e.id == id)
+    }
+
+    pub fn insert_event(&mut self, time: Time, payload: impl Into<String>) -> KeyId {
+        let id = KeyId(self.next_event_id);
+        self.next_event_id += 1;
+        self.insert_sorted(TrackEvent {
+            id,
+            time,
+            payload: payload.into(),
+        });
+        id
+    }
+
+    pub fn remove_event(&mut self, id: KeyId) -> Option<TrackEvent> {
+        let index = self.events.iter().position(|e| e.id == id)?;
+        Some(self.events.remove(index))
+    }
+
+    pub fn move_event(&mut self, id: KeyId, time: Time) -> bool {
+        match self.remove_event(id) {
+            Some(mut event) => {
+                event.time = time;
+                self.insert_sorted(event);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn set_payload(&mut self, id: KeyId, payload: impl Into<String>) -> bool {
+        match self.events.iter_mut().find(|e| e.id == id) {
+            Some(event) => {
+                event.payload = payload.into();
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn insert_sorted(&mut self, event: TrackEvent) {
+        let index = self.events.partition_point(|e| e.time <= event.time);
+        self.events.insert(index, event);
+    }
+}
+
+/// Saved form of an [`EventTrack`], event ids are not kept.
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+struct EventTrackData {
+    name: String,
+    default_payload: String,
+    events: Vec<EventData>,
+}
+
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+struct EventData {
+    time: Time,
+    payload: String,
+}
+
+#[cfg(feature = "serde")]
+impl EventTrack {
+    /// Recreates a track from [`Track::save`] data, `None` if the data is invalid.
+    pub fn load(data: TrackData) -> Option<Self> {
+        let data: EventTrackData = data.deserialize_into().ok()?;
+        let mut track = Self::new(data.name).with_default_payload(data.default_payload);
+        for event in data.events {
+            track.insert_event(event.time, event.payload);
+        }
+        Some(track)
+    }
+}
+
+impl Track for EventTrack {
+    fn draw_head(&mut self, ui: &imgui::Ui) {
+        draw_track_name(ui, &self.name, Self::TRACK_HEIGHT);
+    }
+
+    fn head_config(&mut self) -> (String, f32) {
+        (
+            format!("event_track_head_{}", self.name),
+            Self::TRACK_HEIGHT,
+        )
+    }
+
+    fn draw_lane(&mut self, ui: &imgui::Ui, view: &TrackView) {
+        let top = view.lane_min.y + 3.0;
+        let bottom = view.lane_max.y - 3.0;
+        let size = Self::FLAG_SIZE;
+        let text_y = (top + bottom - ui.text_line_height()) / 2.0;
+
+        for (index, event) in self.events.iter().enumerate() {
+            // Labels run up to the next event, so one left of the view may still show.
+            let next_x = self
+                .events
+                .get(index + 1)
+                .map_or(view.lane_max.x, |next| view.time_to_x(next.time));
+            if next_x < view.lane_min.x || event.time > view.right_time {
+                continue;
+            }
+            let x = view.time_to_x(event.time);
+            let color = if view.is_selected(event.id) {
+                [1.0, 0.85, 0.6]
+            } else {
+                [0.4, 0.75, 0.45]
+            };
+            view.draw_list
+                .add_line([x, top], [x, bottom], color)
+                .thickness(2.0)
+                .build();
+            view.draw_list
+                .add_triangle(
+                    [x, top],
+                    [x + size * 1.5, top + size],
+                    [x, top + size * 2.0],
+                    color,
+                )
+                .filled(true)
+                .build();
+            view.draw_list.with_clip_rect_intersect(
+                [x + size * 1.5, view.lane_min.y],
+                [next_x - 2.0, view.lane_max.y],
+                || {
+                    view.draw_list.add_text(
+                        [x + size * 1.5 + 2.0, text_y],
+                        [0.85; 3],
+                        &event.payload,
+                    );
+                },
+            );
+        }
+    }
+
+    fn key_times(&self) -> Vec<(KeyId, Time)> {
+        self.events.iter().map(|e| (e.id, e.time)).collect()
+    }
+
+    fn add_key_at(&mut self, time: Time) -> Option<KeyId> {
+        Some(self.insert_event(time, self.default_payload.clone()))
+    }
+
+    fn move_key(&mut self, key: KeyId, time: Time) -> bool {
+        self.move_event(key, time)
+    }
+
+    fn delete_key(&mut self, key: KeyId) -> bool {
+        self.remove_event(key).is_some()
+    }
+
+    fn trigger_times(&self) -> Vec<(KeyId, Time)> {
+        self.key_times()
+    }
+
+    fn clipboard_type(&self) -> Option<&str> {
+        Some(Self::TYPE_TAG)
+    }
+
+    /// The escaped payload.
+    fn copy_key(&self, key: KeyId) -> Option<String> {
+        Some(escape_field(&self.event(key)?.payload))
+    }
+
+    fn paste_key(&mut self, time: Time, text: &str) -> Result<KeyId, PasteError> {
+        let payload =
+            unescape_field(text).ok_or_else(|| PasteError::InvalidKey(text.to_string()))?;
+        Ok(self.insert_event(time, payload))
+    }
+
+    fn key_state(&self, key: KeyId) -> Option<KeyState> {
+        Some(Rc::new(self.event(key)?.clone()))
+    }
+
+    fn restore_key(&mut self, state: &dyn Any) -> bool {
+        let Some(event) = state.downcast_ref::<TrackEvent>() else {
+            return false;
+        };
+        self.remove_event(event.id);
+        self.next_event_id = self.next_event_id.max(event.id.0 + 1);
+        self.insert_sorted(event.clone());
+        true
+    }
+
+    fn name(&self) -> Option<&str> {
+        Some(&self.name)
+    }
+
+    #[cfg(feature = "serde")]
+    fn type_tag(&self) -> Option<String> {
+        Some(Self::TYPE_TAG.to_string())
+    }
+
+    #[cfg(feature = "serde")]
+    fn save(&self) -> Option<TrackData> {
+        let events = self
+            .events
+            .iter()
+            .map(|event| EventData {
+                time: event.time,
+                payload: event.payload.clone(),
+            })
+            .collect();
+        serde_value::to_value(EventTrackData {
+            name: self.name.clone(),
+            default_payload: self.default_payload.clone(),
+            events,
+        })
+        .ok()
+    }
+}
@@ -0,0 +1,793 @@
+use imgui::{
+    sys::{ImGuiKey_A, ImGuiKey_F},
+    MouseButton,
+};
+
+use crate::{
+    time_from_offset,
+    view::{KEY_HIT_RADIUS, MAX_TIME_SCALE, MIN_TIME_SCALE, SECOND_WIDTH, ZOOM_STEP},
+    AsArray, Interpolation, KeyId, KeyRef, KeyState, Point, Time, Timeline, TimelineEvent, TrackId,
+};
+
+/// Curve colors by component, x/r, y/g, z/b and w/a.
+const CURVE_COLORS: [[f32; 3]; 4] = [
+    [0.9, 0.35, 0.35],
+    [0.4, 0.85, 0.4],
+    [0.4, 0.6, 1.0],
+    [0.75, 0.75, 0.75],
+];
+const SINGLE_CURVE_COLOR: [f32; 3] = [0.86, 0.554, 0.33];
+const SELECTED_KEY_COLOR: [f32; 3] = [1.0, 0.85, 0.6];
+const HANDLE_COLOR: [f32; 3] = [0.8, 0.8, 0.5];
+const KEY_RADIUS: f32 = 4.0;
+const HANDLE_RADIUS: f32 = 3.0;
+/// Horizontal pixels between curve samples.
+const CURVE_STEP: f32 = 2.0;
+/// Value grid lines are at least this many pixels apart.
+const MIN_GRID_SPACING: f32 = 30.0;
+/// Space kept around framed keys, in pixels.
+const FRAME_MARGIN: f32 = 20.0;
+/// Pixels per value unit.
+const MIN_VALUE_SCALE: f32 = 1e-4;
+const MAX_VALUE_SCALE: f32 = 1e6;
+
+/// The editor shown below the ruler.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum EditorMode {
+    /// A lane of keys per track.
+    #[default]
+    DopeSheet,
+    /// Value curves of the tracks in [`Timeline::graph_tracks`].
+    Graph,
+}
+
+impl EditorMode {
+    pub const ALL: [Self; 2] = [Self::DopeSheet, Self::Graph];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::DopeSheet => "Dope sheet",
+            Self::Graph => "Graph",
+        }
+    }
+}
+
+pub(crate) struct GraphView {
+    tracks: Vec<TrackId>,
+    /// Value at the vertical center of the plot.
+    value_center: f32,
+    /// Pixels per value unit.
+    value_scale: f32,
+    /// Plot rectangle of the last draw.
+    min: Point,
+    max: Point,
+    drag: Option<GraphDrag>,
+}
+
+impl Default for GraphView {
+    fn default() -> Self {
+        Self {
+            tracks: Vec::new(),
+            value_center: 0.0,
+            value_scale: 100.0,
+            min: Point::new(0.0, 0.0),
+            max: Point::new(0.0, 0.0),
+            drag: None,
+        }
+    }
+}
+
+impl GraphView {
+    pub(crate) fn is_dragging(&self) -> bool {
+        self.drag.is_some()
+    }
+
+    fn center_y(&self) -> f32 {
+        (self.min.y + self.max.y) / 2.0
+    }
+
+    fn value_to_y(&self, value: f32) -> f32 {
+        self.center_y() - (value - self.value_center) * self.value_scale
+    }
+
+    fn y_to_value(&self, y: f32) -> f32 {
+        self.value_center + (self.center_y() - y) / self.value_scale
+    }
+}
+
+enum GraphDrag {
+    /// The selected keys follow the mouse, their values change on the grabbed curve.
+    Keys {
+        curve: usize,
+        origin: (Time, f32),
+        keys: Vec<DraggedKey>,
+    },
+    /// A bezier tangent of the segment from `key` to `next`.
+    Tangent {
+        key: KeyRef,
+        next: KeyId,
+        curve: usize,
+        side: Side,
+        before: Option<(Interpolation, KeyState)>,
+    },
+}
+
+struct DraggedKey {
+    key: KeyRef,
+    time: Time,
+    /// Value on the grabbed curve when the drag started.
+    value: Option<f32>,
+    current: Time,
+    before: Option<KeyState>,
+}
+
+#[derive(Clone, Copy)]
+enum Side {
+    Out,
+    In,
+}
+
+#[derive(Clone, Copy)]
+enum GraphHit {
+    Key {
+        key: KeyRef,
+        curve: usize,
+    },
+    Tangent {
+        key: KeyRef,
+        next: KeyId,
+        curve: usize,
+        side: Side,
+    },
+}
+
+impl Timeline {
+    pub fn editor_mode(&self) -> EditorMode {
+        self.editor_mode
+    }
+
+    /// Switches the editor, ending drags. Entering the graph with no tracks shown shows the
+    /// tracks of the selected keys.
+    pub fn set_editor_mode(&mut self, mode: EditorMode) {
+        if mode == self.editor_mode {
+            return;
+        }
+        self.end_drags();
+        if mode == EditorMode::Graph && self.graph.tracks.is_empty() {
+            let tracks = self
+                .selection
+                .iter()
+                .map(|key| key.track)
+                .collect::<Vec<_>>();
+            self.set_graph_tracks(tracks);
+        }
+        self.editor_mode = mode;
+    }
+
+    /// Tracks whose curves the graph editor plots.
+    pub fn graph_tracks(&self) -> &[TrackId] {
+        &self.graph.tracks
+    }
+
+    /// Replaces the plotted tracks, unknown tracks and tracks without curves are ignored.
+    pub fn set_graph_tracks(&mut self, tracks: impl IntoIterator<Item = TrackId>) {
+        self.graph.tracks.clear();
+        for track in tracks {
+            self.show_in_graph(track, true);
+        }
+    }
+
+    pub fn show_in_graph(&mut self, track: TrackId, show: bool) {
+        let plottable = self.track(track).is_some_and(|t| t.curve_count() > 0);
+        self.graph.tracks.retain(|t| *t != track);
+        if show && plottable {
+            self.graph.tracks.push(track);
+        }
+    }
+
+    /// Zooms the graph to the keys of all plotted tracks.
+    pub fn frame_graph_all(&mut self) {
+        self.frame_graph(false);
+    }
+
+    /// Zooms the graph to the selected keys, or to all keys if none of them is plotted.
+    pub fn frame_graph_selection(&mut self) {
+        if !self.frame_graph(true) {
+            self.frame_graph_all();
+        }
+    }
+
+    /// Fits the time and value range of the plotted keys into the last drawn plot, `false` if
+    /// there are no keys to fit.
+    fn frame_graph(&mut self, selected_only: bool) -> bool {
+        let mut points = Vec::new();
+        for (track_id, track) in self.graph_plot_tracks() {
+            for (key, time) in track.key_times() {
+                if selected_only && !self.is_key_selected(KeyRef::new(track_id, key)) {
+                    continue;
+                }
+                for curve in 0..track.curve_count() {
+                    if let Some(value) = track.key_curve_value(key, curve) {
+                        points.push((time, value));
+                    }
+                }
+            }
+        }
+        let Some(&(first_time, first_value)) = points.first() else {
+            return false;
+        };
+        let (start, end, low, high) = points.iter().fold(
+            (first_time, first_time, first_value, first_value),
+            |(start, end, low, high), &(time, value)| {
+                (
+                    start.min(time),
+                    end.max(time),
+                    low.min(value),
+                    high.max(value),
+                )
+            },
+        );
+
+        let size = self.graph.max - self.graph.min;
+        let width = size.x - 2.0 * FRAME_MARGIN;
+        if width > 0.0 {
+            if end > start {
+                self.time_scale = (width / (end - start).as_seconds() as f32 / SECOND_WIDTH)
+                    .clamp(MIN_TIME_SCALE, MAX_TIME_SCALE);
+            }
+            let second_width = SECOND_WIDTH * self.time_scale;
+            let center = start + (end - start) / 2;
+            self.left_time = time_from_offset(center, -size.x / 2.0, second_width);
+        }
+        let height = size.y - 2.0 * FRAME_MARGIN;
+        if height > 0.0 && high - low > f32::EPSILON {
+            self.graph.value_scale =
+                (height / (high - low)).clamp(MIN_VALUE_SCALE, MAX_VALUE_SCALE);
+        }
+        self.graph.value_center = (low + high) / 2.0;
+        true
+    }
+
+    /// The shown tracks that still exist and have curves.
+    fn graph_plot_tracks(&self) -> Vec<(TrackId, &dyn crate::Track)> {
+        self.graph
+            .tracks
+            .iter()
+            .filter_map(|id| Some((*id, self.track(*id)?)))
+            .filter(|(_, track)| track.curve_count() > 0)
+            .collect()
+    }
+
+    /// The list of tracks that can be plotted, next to the graph.
+    pub(crate) fn draw_graph_track_list(&mut self, ui: &imgui::Ui, height: f32) {
+        let Some(_child) = ui.child_window("graph_tracks").size([0.0, height]).begin() else {
+            return;
+        };
+        if ui.small_button("Frame all") {
+            self.frame_graph_all();
+        }
+        ui.same_line();
+        if ui.small_button("Frame selection") {
+            self.frame_graph_selection();
+        }
+
+        let mut toggled = None;
+        for entry in &self.tracks {
+            if entry.track.curve_count() == 0 {
+                continue;
+            }
+            let shown = self.graph.tracks.contains(&entry.id);
+            let label = entry
+                .track
+                .name()
+                .map_or_else(|| format!("Track {}", entry.id.0), str::to_string);
+            let _id = ui.push_id_usize(entry.id.0 as usize);
+            if ui.selectable_config(label).selected(shown).build() {
+                toggled = Some((entry.id, !shown));
+            }
+        }
+        if let Some((track, show)) = toggled {
+            self.show_in_graph(track, show);
+        }
+    }
+
+    /// Plots the curves of the shown tracks and handles editing them. Wheel zooms the values,
+    /// middle-dragging pans them along with the time.
+    pub(crate) fn draw_graph(
+        &mut self,
+        ui: &imgui::Ui,
+        wdl: &imgui::DrawListMut<'_>,
+        second_width: f32,
+        height: f32,
+        focused: bool,
+    ) {
+        let Some(_child) = ui
+            .child_window("graph")
+            .scroll_bar(false)
+            .scrollable(false)
+            .size([0.0, height])
+            .begin()
+        else {
+            return;
+        };
+        let min: Point = ui.window_pos().into();
+        let max = min + Point::from(ui.window_size());
+        self.graph.min = min;
+        self.graph.max = max;
+        let hovered = ui.is_window_hovered();
+        let io = ui.io();
+        let mouse: Point = io.mouse_pos.into();
+
+        let mut hits = Vec::new();
+        wdl.with_clip_rect(min.array(), max.array(), || {
+            self.draw_value_grid(ui, wdl);
+            for (track_id, track) in self.graph_plot_tracks() {
+                let count = track.curve_count();
+                let mut keys = track.key_times();
+                keys.sort_by_key(|(_, time)| *time);
+                let point = |key: KeyId, time: Time, curve: usize| {
+                    let value = track.key_curve_value(key, curve)?;
+                    Some(Point::new(
+                        min.x + (time - self.left_time).as_seconds() as f32 * second_width,
+                        self.graph.value_to_y(value),
+                    ))
+                };
+
+                for curve in 0..count {
+                    let color = if count == 1 {
+                        SINGLE_CURVE_COLOR
+                    } else {
+                        CURVE_COLORS[curve % CURVE_COLORS.len()]
+                    };
+
+                    let mut line = Vec::new();
+                    let mut x = min.x;
+                    while x < max.x + CURVE_STEP {
+                        let time = time_from_offset(self.left_time, x - min.x, second_width);
+                        if let Some(value) = track.sample_curve(time, curve) {
+                            line.push([x, self.graph.value_to_y(value)]);
+                        }
+                        x += CURVE_STEP;
+                    }
+                    if line.len() > 1 {
+                        wdl.add_polyline(line, color).thickness(1.5).build();
+                    }
+
+                    for pair in keys.windows(2) {
+                        let ((key, time), (next, next_time)) = (pair[0], pair[1]);
+                        let key_ref = KeyRef::new(track_id, key);
+                        if !self.is_key_selected(key_ref)
+                            && !self.is_key_selected(KeyRef::new(track_id, next))
+                        {
+                            continue;
+                        }
+                        let Some(Interpolation::Bezier {
+                            out_tangent,
+                            in_tangent,
+                        }) = track.key_interpolation(key)
+                        else {
+                            continue;
+                        };
+                        let (Some(from), Some(to)) =
+                            (point(key, time, curve), point(next, next_time, curve))
+                        else {
+                            continue;
+                        };
+                        for (side, tangent, anchor) in
+                            [(Side::Out, out_tangent, from), (Side::In, in_tangent, to)]
+                        {
+                            let handle = Point::new(
+                                from.x + tangent[0] * (to.x - from.x),
+                                from.y + tangent[1] * (to.y - from.y),
+                            );
+                            wdl.add_line(anchor.array(), handle.array(), HANDLE_COLOR)
+                                .build();
+                            wdl.add_circle(handle.array(), HANDLE_RADIUS, HANDLE_COLOR)
+                                .filled(true)
+                                .build();
+                            let hit = GraphHit::Tangent {
+                                key: key_ref,
+                                next,
+                                curve,
+                                side,
+                            };
+                            hits.push((hit, handle));
+                        }
+                    }
+
+                    for &(key, time) in &keys {
+                        let Some(position) = point(key, time, curve) else {
+                            continue;
+                        };
+                        let key = KeyRef::new(track_id, key);
+                        let fill = if self.is_key_selected(key) {
+                            SELECTED_KEY_COLOR
+                        } else {
+                            color
+                        };
+                        wdl.add_circle(position.array(), KEY_RADIUS, fill)
+                            .filled(true)
+                            .build();
+                        wdl.add_circle(position.array(), KEY_RADIUS, [0.0, 0.0, 0.0])
+                            .build();
+                        hits.push((GraphHit::Key { key, curve }, position));
+                    }
+                }
+            }
+        });
+
+        let hit = hits
+            .into_iter()
+            .map(|(hit, position)| (hit, (position.x - mouse.x).hypot(position.y - mouse.y)))
+            .filter(|(_, distance)| *distance <= KEY_HIT_RADIUS)
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(hit, _)| hit);
+
+        if hovered && self.graph.drag.is_none() && !self.scrubbing {
+            if ui.is_mouse_clicked(MouseButton::Left) {
+                match hit {
+                    Some(GraphHit::Key { key, curve }) => {
+                        self.click_key(key, io.key_ctrl, io.key_shift);
+                        if self.is_key_selected(key) {
+                            self.begin_graph_key_drag(curve, mouse, second_width);
+                        }
+                    }
+                    Some(GraphHit::Tangent {
+                        key,
+                        next,
+                        curve,
+                        side,
+                    }) => {
+                        let before = self.track(key.track).and_then(|track| {
+                            Some((track.key_interpolation(key.key)?, track.key_state(key.key)?))
+                        });
+                        self.graph.drag = Some(GraphDrag::Tangent {
+                            key,
+                            next,
+                            curve,
+                            side,
+                            before,
+                        });
+                    }
+                    None => {
+                        if !io.key_ctrl && !io.key_shift {
+                            self.selection.clear();
+                        }
+                    }
+                }
+            }
+            if ui.is_mouse_clicked(MouseButton::Right) {
+                if let Some(GraphHit::Key { key, .. }) = hit {
+                    if !self.is_key_selected(key) {
+                        self.selection = vec![key];
+                    }
+                    ui.open_popup("graph_key_menu");
+                }
+            }
+        }
+        self.draw_graph_key_menu(ui);
+        self.update_graph_drag(ui, mouse, second_width);
+
+        if hovered && !io.key_ctrl && !io.key_shift && io.mouse_wheel != 0.0 {
+            let anchor = self.graph.y_to_value(mouse.y);
+            self.graph.value_scale = (self.graph.value_scale * ZOOM_STEP.powf(io.mouse_wheel))
+                .clamp(MIN_VALUE_SCALE, MAX_VALUE_SCALE);
+            self.graph.value_center =
+                anchor - (self.graph.center_y() - mouse.y) / self.graph.value_scale;
+        }
+        if self.panning && ui.is_mouse_down(MouseButton::Middle) {
+            self.graph.value_center += io.mouse_delta[1] / self.graph.value_scale;
+        }
+
+        if focused && self.takes_shortcuts(ui) && !io.key_ctrl {
+            if ui.is_key_index_pressed_no_repeat(ImGuiKey_F) {
+                self.frame_graph_selection();
+            }
+            if ui.is_key_index_pressed_no_repeat(ImGuiKey_A) {
+                self.frame_graph_all();
+            }
+        }
+
+        if let Some(time) = self.snap_guide {
+            let x = min.x + (time - self.left_time).as_seconds() as f32 * second_width;
+            wdl.with_clip_rect(min.array(), max.array(), || {
+                wdl.add_line([x, min.y], [x, max.y], [0.3, 0.9, 1.0, 0.8])
+                    .build();
+            });
+        }
+    }
+
+    /// Horizontal lines at round values with their labels on the left.
+    fn draw_value_grid(&self, ui: &imgui::Ui, wdl: &imgui::DrawListMut<'_>) {
+        let raw_step = MIN_GRID_SPACING / self.graph.value_scale;
+        let magnitude = 10f32.powf(raw_step.log10().floor());
+        let step = [1.0, 2.0, 5.0, 10.0]
+            .into_iter()
+            .map(|factor| factor * magnitude)
+            .find(|step| *step >= raw_step)
+            .unwrap_or(10.0 * magnitude);
+        let decimals = (-step.log10().floor()).max(0.0) as usize;
+
+        let top = self.graph.y_to_value(self.graph.min.y);
+        let bottom = self.graph.y_to_value(self.graph.max.y);
+        let text_height = ui.text_line_height();
+        let mut index = (bottom / step).floor() as i64;
+        while (index as f32) * step <= top {
+            let value = index as f32 * step;
+            let y = self.graph.value_to_y(value);
+            let color = if index == 0 {
+                [0.6, 0.6, 0.6, 0.8]
+            } else {
+                [0.4, 0.4, 0.4, 0.5]
+            };
+            wdl.add_line([self.graph.min.x, y], [self.graph.max.x, y], color)
+                .build();
+            wdl.add_text(
+                [self.graph.min.x + 3.0, y - text_height],
+                [0.6, 0.6, 0.6],
+                format!("{:.*}", decimals, value),
+            );
+            index += 1;
+        }
+    }
+
+    fn begin_graph_key_drag(&mut self, curve: usize, mouse: Point, second_width: f32) {
+        self.prune_selection();
+        let mut keys = Vec::new();
+        for key in &self.selection {
+            let Some(track) = self.track(key.track) else {
+                continue;
+            };
+            let Some((_, time)) = track.key_times().into_iter().find(|(k, _)| *k == key.key) else {
+                continue;
+            };
+            keys.push(DraggedKey {
+                key: *key,
+                time,
+                value: track.key_curve_value(key.key, curve),
+                current: time,
+                before: track.key_state(key.key),
+            });
+        }
+        let origin = (
+            time_from_offset(self.left_time, mouse.x - self.graph.min.x, second_width),
+            self.graph.y_to_value(mouse.y),
+        );
+        self.begin_group();
+        self.graph.drag = Some(GraphDrag::Keys {
+            curve,
+            origin,
+            keys,
+        });
+    }
+
+    fn update_graph_drag(&mut self, ui: &imgui::Ui, mouse: Point, second_width: f32) {
+        let Some(mut drag) = self.graph.drag.take() else {
+            return;
+        };
+        if !ui.is_mouse_down(MouseButton::Left) {
+            self.finish_graph_drag(drag);
+            return;
+        }
+        match &mut drag {
+            GraphDrag::Keys {
+                curve,
+                origin,
+                keys,
+            } => {
+                let time =
+                    time_from_offset(self.left_time, mouse.x - self.graph.min.x, second_width);
+                let delta = time - origin.0;
+                let value_delta = self.graph.y_to_value(mouse.y) - origin.1;
+                // Same snapping as dragging keys in the lanes, Shift disables it.
+                let shift = ui.io().key_shift;
+                let snap = if shift {
+                    None
+                } else {
+                    let times = keys.iter().map(|k| k.time + delta).collect::<Vec<_>>();
+                    let ignore = keys.iter().map(|k| k.key).collect::<Vec<_>>();
+                    self.find_snap(&times, &ignore, true, second_width)
+                };
+                self.snap_guide = snap.map(|(_, target)| target);
+                let mut pending = Vec::new();
+                for dragged in keys.iter_mut() {
+                    let time = match snap {
+                        Some((offset, _)) => dragged.time + delta + offset,
+                        None if shift => dragged.time + delta,
+                        None => self.snap_time(dragged.time + delta),
+                    };
+                    let Some(track) = self.track_mut(dragged.key.track) else {
+                        continue;
+                    };
+                    if let Some(value) = dragged.value {
+                        track.set_key_curve_value(dragged.key.key, *curve, value + value_delta);
+                    }
+                    if time != dragged.current {
+                        pending.push((dragged, time));
+                    }
+                }
+                // A key blocked by another dragged key moves once the other one made room.
+                while !pending.is_empty() {
+                    let count = pending.len();
+                    pending.retain_mut(|(dragged, time)| {
+                        if !self
+                            .track_mut(dragged.key.track)
+                            .is_some_and(|t| t.move_key(dragged.key.key, *time))
+                        {
+                            return true;
+                        }
+                        self.emit(TimelineEvent::KeyMoved {
+                            key: dragged.key,
+                            from: dragged.current,
+                            to: *time,
+                        });
+                        dragged.current = *time;
+                        false
+                    });
+                    if pending.len() == count {
+                        break;
+                    }
+                }
+            }
+            GraphDrag::Tangent {
+                key,
+                next,
+                curve,
+                side,
+                ..
+            } => {
+                let interpolation =
+                    self.dragged_tangent(*key, *next, *curve, *side, mouse, second_width);
+                if let (Some(interpolation), Some(track)) =
+                    (interpolation, self.track_mut(key.track))
+                {
+                    track.set_key_interpolation(key.key, interpolation);
+                }
+            }
+        }
+        self.graph.drag = Some(drag);
+    }
+
+    /// The interpolation of `key` with one tangent moved to the mouse. Tangent times stay
+    /// between the two keys, values are only changed where the keys' values differ.
+    fn dragged_tangent(
+        &self,
+        key: KeyRef,
+        next: KeyId,
+        curve: usize,
+        side: Side,
+        mouse: Point,
+        second_width: f32,
+    ) -> Option<Interpolation> {
+        let track = self.track(key.track)?;
+        let Some(Interpolation::Bezier {
+            mut out_tangent,
+            mut in_tangent,
+        }) = track.key_interpolation(key.key)
+        else {
+            return None;
+        };
+        let times = track.key_times();
+        let time_of = |id: KeyId| times.iter().find(|(k, _)| *k == id).map(|(_, t)| *t);
+        let x_of = |time: Time| {
+            self.graph.min.x + (time - self.left_time).as_seconds() as f32 * second_width
+        };
+        let from = Point::new(
+            x_of(time_of(key.key)?),
+            self.graph
+                .value_to_y(track.key_curve_value(key.key, curve)?),
+        );
+        let to = Point::new(
+            x_of(time_of(next)?),
+            self.graph.value_to_y(track.key_curve_value(next, curve)?),
+        );
+
+        let tangent = match side {
+            Side::Out => &mut out_tangent,
+            Side::In => &mut in_tangent,
+        };
+        let size = to - from;
+        if size.x.abs() > f32::EPSILON {
+            tangent[0] = ((mouse.x - from.x) / size.x).clamp(0.0, 1.0);
+        }
+        if size.y.abs() >= 1.0 {
+            tangent[1] = (mouse.y - from.y) / size.y;
+        }
+        Some(Interpolation::Bezier {
+            out_tangent,
+            in_tangent,
+        })
+    }
+
+    /// Records the drag's edits as one undo step.
+    /// Ends key, trim, box and graph drags, keeping what they changed so far.
+    pub(crate) fn end_drags(&mut self) {
+        if self.key_drag.take().is_some() {
+            self.end_group();
+        }
+        self.key_trim = None;
+        self.box_select = None;
+        if let Some(drag) = self.graph.drag.take() {
+            self.finish_graph_drag(drag);
+        }
+    }
+
+    fn finish_graph_drag(&mut self, drag: GraphDrag) {
+        self.snap_guide = None;
+        match drag {
+            GraphDrag::Keys { curve, keys, .. } => {
+                for dragged in keys {
+                    let key = dragged.key;
+                    let Some(track) = self.track(key.track) else {
+                        continue;
+                    };
+                    let value_changed = track.key_curve_value(key.key, curve) != dragged.value;
+                    if !value_changed && dragged.current == dragged.time {
+                        continue;
+                    }
+                    if let (Some(before), Some(after)) = (dragged.before, track.key_state(key.key))
+                    {
+                        self.record_change_key(key, before, after);
+                    }
+                    if value_changed {
+                        self.emit(TimelineEvent::KeyChanged(key));
+                    }
+                }
+                self.end_group();
+            }
+            GraphDrag::Tangent { key, before, .. } => {
+                let Some(track) = self.track(key.track) else {
+                    return;
+                };
+                let (Some((interpolation, before)), Some(after)) =
+                    (before, track.key_state(key.key))
+                else {
+                    return;
+                };
+                if track.key_interpolation(key.key) != Some(interpolation) {
+                    self.record_change_key(key, before, after);
+                    self.emit(TimelineEvent::KeyChanged(key));
+                }
+            }
+        }
+    }
+
+    fn draw_graph_key_menu(&mut self, ui: &imgui::Ui) {
+        ui.popup("graph_key_menu", || {
+            let choices = [
+                ("Constant", Interpolation::Constant),
+                ("Linear", Interpolation::Linear),
+                ("Ease in/out", Interpolation::EASE_IN_OUT),
+            ];
+            for (name, interpolation) in choices {
+                if ui.menu_item(name) {
+                    self.set_selection_interpolation(interpolation);
+                }
+            }
+        });
+    }
+
+    /// Sets the interpolation of every selected key that has one, as one undo step.
+    fn set_selection_interpolation(&mut self, interpolation: Interpolation) {
+        self.begin_group();
+        for key in self.selection.clone() {
+            let Some(track) = self.track_mut(key.track) else {
+                continue;
+            };
+            let before = track.key_state(key.key);
+            if track
+                .key_interpolation(key.key)
+                .is_none_or(|i| i == interpolation)
+                || !track.set_key_interpolation(key.key, interpolation)
+            {
+                continue;
+            }
+            if let (Some(before), Some(after)) = (before, track.key_state(key.key)) {
+                self.record_change_key(key, before, after);
+            }
+            self.emit(TimelineEvent::KeyChanged(key));
+        }
+        self.end_group();
+    }
+}
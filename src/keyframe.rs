@@ -0,0 +1,873 @@
+use std::{any::Any, rc::Rc};
+
+#[cfg(feature = "serde")]
+use crate::TrackData;
+use crate::{view::draw_track_name, KeyId, KeyState, PasteError, Time, Track, TrackView};
+
+/// A sampled track value with its type erased, see [`crate::Timeline::sample_all`].
+#[derive(Debug, Clone)]
+pub enum Value {
+    Float(f32),
+    Double(f64),
+    Bool(bool),
+    Vec2(cgmath::Vector2<f32>),
+    Vec3(cgmath::Vector3<f32>),
+    Vec4(cgmath::Vector4<f32>),
+    Rgb([f32; 3]),
+    Rgba([f32; 4]),
+    /// Any other [`KeyValue`], downcast it to the track's value type.
+    Other(Rc<dyn Any>),
+}
+
+/// A value that can be stored in a [`KeyFrameTrack`] and blended between keys.
+pub trait KeyValue: Clone + 'static {
+    /// Blends from `self` (`t == 0.0`) to `other` (`t == 1.0`).
+    fn lerp(&self, other: &Self, t: f32) -> Self;
+    /// Value of a new key on an empty track.
+    fn zero() -> Self;
+
+    fn to_value(&self) -> Value {
+        Value::Other(Rc::new(self.clone()))
+    }
+
+    /// Names the type in clipboard text and saved documents, keys are only pasted onto
+    /// tracks of the same type. Keep it unchanged once documents were saved with it.
+    #[cfg(feature = "serde")]
+    fn type_name() -> &'static str;
+    /// Names the type in clipboard text, keys are only pasted onto tracks of the same type.
+    #[cfg(not(feature = "serde"))]
+    fn type_name() -> &'static str {
+        std::any::type_name::<Self>()
+    }
+    /// The value as clipboard text, `None` if it can't be copied.
+    fn to_text(&self) -> Option<String> {
+        None
+    }
+    fn from_text(_text: &str) -> Option<Self> {
+        None
+    }
+
+    /// Components plotted as curves in the graph editor, empty if the value can't be plotted.
+    fn curve_values(&self) -> Vec<f32> {
+        Vec::new()
+    }
+    /// The value with one component replaced, `None` if it can't be edited as a curve.
+    fn with_curve_value(&self, _curve: usize, _value: f32) -> Option<Self> {
+        None
+    }
+
+    /// The value in a saved document, tracks with values that can't be saved aren't saved.
+    #[cfg(feature = "serde")]
+    fn to_data(&self) -> Option<TrackData> {
+        None
+    }
+    #[cfg(feature = "serde")]
+    fn from_data(_data: TrackData) -> Option<Self> {
+        None
+    }
+}
+
+// Values that implement serde are saved as they serialize.
+#[cfg(feature = "serde")]
+macro_rules! serde_data {
+    () => {
+        fn to_data(&self) -> Option<TrackData> {
+            serde_value::to_value(self).ok()
+        }
+
+        fn from_data(data: TrackData) -> Option<Self> {
+            data.deserialize_into().ok()
+        }
+    };
+}
+
+#[cfg(not(feature = "serde"))]
+macro_rules! serde_data {
+    () => {};
+}
+
+impl KeyValue for f32 {
+    fn lerp(&self, other: &Self, t: f32) -> Self {
+        self + (other - self) * t
+    }
+
+    fn zero() -> Self {
+        0.0
+    }
+
+    fn to_value(&self) -> Value {
+        Value::Float(*self)
+    }
+
+    fn type_name() -> &'static str {
+        "f32"
+    }
+
+    fn curve_values(&self) -> Vec<f32> {
+        vec![*self]
+    }
+
+    fn with_curve_value(&self, _curve: usize, value: f32) -> Option<Self> {
+        Some(value)
+    }
+
+    fn to_text(&self) -> Option<String> {
+        Some(self.to_string())
+    }
+
+    fn from_text(text: &str) -> Option<Self> {
+        text.trim().parse().ok()
+    }
+
+    serde_data!();
+}
+
+impl KeyValue for f64 {
+    fn lerp(&self, other: &Self, t: f32) -> Self {
+        self + (other - self) * t as f64
+    }
+
+    fn zero() -> Self {
+        0.0
+    }
+
+    fn to_value(&self) -> Value {
+        Value::Double(*self)
+    }
+
+    fn type_name() -> &'static str {
+        "f64"
+    }
+
+    fn curve_values(&self) -> Vec<f32> {
+        vec![*self as f32]
+    }
+
+    fn with_curve_value(&self, _curve: usize, value: f32) -> Option<Self> {
+        Some(value as f64)
+    }
+
+    fn to_text(&self) -> Option<String> {
+        Some(self.to_string())
+    }
+
+    fn from_text(text: &str) -> Option<Self> {
+        text.trim().parse().ok()
+    }
+
+    serde_data!();
+}
+
+impl KeyValue for bool {
+    fn lerp(&self, other: &Self, t: f32) -> Self {
+        if t < 1.0 {
+            *self
+        } else {
+            *other
+        }
+    }
+
+    fn zero() -> Self {
+        false
+    }
+
+    fn to_value(&self) -> Value {
+        Value::Bool(*self)
+    }
+
+    fn type_name() -> &'static str {
+        "bool"
+    }
+
+    fn to_text(&self) -> Option<String> {
+        Some(self.to_string())
+    }
+
+    fn from_text(text: &str) -> Option<Self> {
+        text.trim().parse().ok()
+    }
+
+    serde_data!();
+}
+
+// Colors are stored the way imgui passes them around, as RGB(A) arrays.
+macro_rules! impl_key_value_color {
+    ($($n: literal => $variant: ident, $name: literal),*) => {
+        $(
+            impl KeyValue for [f32; $n] {
+                fn lerp(&self, other: &Self, t: f32) -> Self {
+                    std::array::from_fn(|i| self[i].lerp(&other[i], t))
+                }
+
+                fn zero() -> Self {
+                    [0.0; $n]
+                }
+
+                fn to_value(&self) -> Value {
+                    Value::$variant(*self)
+                }
+
+                fn type_name() -> &'static str {
+                    $name
+                }
+
+                fn to_text(&self) -> Option<String> {
+                    Some(components_to_text(self))
+                }
+
+                fn curve_values(&self) -> Vec<f32> {
+                    self.to_vec()
+                }
+
+                fn with_curve_value(&self, curve: usize, value: f32) -> Option<Self> {
+                    let mut color = *self;
+                    *color.get_mut(curve)? = value;
+                    Some(color)
+                }
+
+                fn from_text(text: &str) -> Option<Self> {
+                    components_from_text(text)
+                }
+
+                serde_data!();
+            }
+        )*
+    };
+}
+
+impl_key_value_color!(3 => Rgb, "rgb", 4 => Rgba, "rgba");
+
+macro_rules! impl_key_value_vector {
+    ($($vector: ident, $n: literal => $variant: ident, $name: literal),*) => {
+        $(
+            impl KeyValue for cgmath::$vector<f32> {
+                fn lerp(&self, other: &Self, t: f32) -> Self {
+                    self + (other - self) * t
+                }
+
+                fn zero() -> Self {
+                    cgmath::Zero::zero()
+                }
+
+                fn to_value(&self) -> Value {
+                    Value::$variant(*self)
+                }
+
+                fn type_name() -> &'static str {
+                    $name
+                }
+
+                fn to_text(&self) -> Option<String> {
+                    let components: [f32; $n] = (*self).into();
+                    Some(components_to_text(&components))
+                }
+
+                fn curve_values(&self) -> Vec<f32> {
+                    let components: [f32; $n] = (*self).into();
+                    components.to_vec()
+                }
+
+                fn with_curve_value(&self, curve: usize, value: f32) -> Option<Self> {
+                    let mut components: [f32; $n] = (*self).into();
+                    *components.get_mut(curve)? = value;
+                    Some(components.into())
+                }
+
+                fn from_text(text: &str) -> Option<Self> {
+                    components_from_text(text).map(Self::from)
+                }
+
+                // Saved as plain arrays so cgmath doesn't need its serde feature.
+                #[cfg(feature = "serde")]
+                fn to_data(&self) -> Option<TrackData> {
+                    let components: [f32; $n] = (*self).into();
+                    serde_value::to_value(components).ok()
+                }
+
+                #[cfg(feature = "serde")]
+                fn from_data(data: TrackData) -> Option<Self> {
+                    data.deserialize_into::<[f32; $n]>().ok().map(Self::from)
+                }
+            }
+        )*
+    };
+}
+
+impl_key_value_vector!(
+    Vector2, 2 => Vec2, "vec2",
+    Vector3, 3 => Vec3, "vec3",
+    Vector4, 4 => Vec4, "vec4"
+);
+
+fn components_to_text(components: &[f32]) -> String {
+    components
+        .iter()
+        .map(f32::to_string)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Parses exactly `N` whitespace separated numbers.
+fn components_from_text<const N: usize>(text: &str) -> Option<[f32; N]> {
+    let mut parts = text.split_whitespace();
+    let mut components = [0.0; N];
+    for component in &mut components {
+        *component = parts.next()?.parse().ok()?;
+    }
+    parts.next().is_none().then_some(components)
+}
+
+/// How the value changes from a key to the next one.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Interpolation {
+    /// Holds the value until the next key.
+    Constant,
+    #[default]
+    Linear,
+    /// Eases along a cubic bezier from `(0, 0)` to `(1, 1)` in normalized time/value space,
+    /// `out_tangent` and `in_tangent` are the two control points.
+    Bezier {
+        out_tangent: [f32; 2],
+        in_tangent: [f32; 2],
+    },
+}
+
+impl Interpolation {
+    pub const EASE_IN_OUT: Self = Self::Bezier {
+        out_tangent: [0.42, 0.0],
+        in_tangent: [0.58, 1.0],
+    };
+
+    /// Maps the normalized time between two keys to the blend factor.
+    pub fn ease(&self, t: f32) -> f32 {
+        match *self {
+            Self::Constant => {
+                if t < 1.0 {
+                    0.0
+                } else {
+                    1.0
+                }
+            }
+            Self::Linear => t,
+            Self::Bezier {
+                out_tangent,
+                in_tangent,
+            } => bezier_ease(out_tangent, in_tangent, t),
+        }
+    }
+}
+
+impl Interpolation {
+    fn to_text(self) -> String {
+        match self {
+            Self::Constant => "constant".to_string(),
+            Self::Linear => "linear".to_string(),
+            Self::Bezier {
+                out_tangent,
+                in_tangent,
+            } => format!(
+                "bezier {}",
+                components_to_text(&[out_tangent[0], out_tangent[1], in_tangent[0], in_tangent[1]])
+            ),
+        }
+    }
+
+    fn from_text(text: &str) -> Option<Self> {
+        let text = text.trim();
+        match text {
+            "constant" => Some(Self::Constant),
+            "linear" => Some(Self::Linear),
+            _ => {
+                let [x1, y1, x2, y2] = components_from_text(text.strip_prefix("bezier ")?)?;
+                Some(Self::Bezier {
+                    out_tangent: [x1, y1],
+                    in_tangent: [x2, y2],
+                })
+            }
+        }
+    }
+}
+
+fn bezier(p1: f32, p2: f32, u: f32) -> f32 {
+    let v = 1.0 - u;
+    3.0 * v * v * u * p1 + 3.0 * v * u * u * p2 + u * u * u
+}
+
+fn bezier_ease(p1: [f32; 2], p2: [f32; 2], t: f32) -> f32 {
+    let t = t.clamp(0.0, 1.0);
+    // Control point x is clamped to 0..1 so x(u) is monotonic and bisection is safe.
+    let (x1, x2) = (p1[0].clamp(0.0, 1.0), p2[0].clamp(0.0, 1.0));
+    let (mut low, mut high) = (0.0, 1.0);
+    let mut u = t;
+    for _ in 0..32 {
+        let x = bezier(x1, x2, u);
+        if (x - t).abs() < 1e-6 {
+            break;
+        }
+        if x < t {
+            low = u;
+        } else {
+            high = u;
+        }
+        u = (low + high) / 2.0;
+    }
+    bezier(p1[1], p2[1], u)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct KeyFrame<V> {
+    id: KeyId,
+    time: Time,
+    value: V,
+    interpolation: Interpolation,
+}
+
+impl<V> KeyFrame<V> {
+    pub fn id(&self) -> KeyId {
+        self.id
+    }
+
+    pub fn time(&self) -> Time {
+        self.time
+    }
+
+    pub fn value(&self) -> &V {
+        &self.value
+    }
+
+    /// Interpolation towards the next key.
+    pub fn interpolation(&self) -> Interpolation {
+        self.interpolation
+    }
+}
+
+pub struct KeyFrameTrack<V = f32> {
+    name: String,
+    keys: Vec<KeyFrame<V>>,
+    next_key_id: u64,
+    default_interpolation: Interpolation,
+}
+
+impl<V: KeyValue> KeyFrameTrack<V> {
+    const TRACK_HEIGHT: f32 = 30.0;
+    const KEY_SIZE: f32 = 6.0;
+
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            keys: Vec::new(),
+            next_key_id: 0,
+            default_interpolation: Interpolation::default(),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Interpolation used for keys created without one.
+    pub fn with_default_interpolation(mut self, interpolation: Interpolation) -> Self {
+        self.default_interpolation = interpolation;
+        self
+    }
+
+    /// The keyframes, sorted by time.
+    pub fn keys(&self) -> &[KeyFrame<V>] {
+        &self.keys
+    }
+
+    /// Inserts a keyframe, replacing the value of an existing key at the same time.
+    pub fn insert_key(&mut self, time: Time, value: V) -> KeyId {
+        self.insert_key_with(time, value, self.default_interpolation)
+    }
+
+    pub fn insert_key_with(&mut self, time: Time, value: V, interpolation: Interpolation) -> KeyId {
+        match self.keys.binary_search_by(|k| k.time.cmp(&time)) {
+            Ok(i) => {
+                self.keys[i].value = value;
+                self.keys[i].interpolation = interpolation;
+                self.keys[i].id
+            }
+            Err(i) => {
+                let id = KeyId(self.next_key_id);
+                self.next_key_id += 1;
+                self.keys.insert(
+                    i,
+                    KeyFrame {
+                        id,
+                        time,
+                        value,
+                        interpolation,
+                    },
+                );
+                id
+            }
+        }
+    }
+
+    pub fn remove_key(&mut self, id: KeyId) -> Option<KeyFrame<V>> {
+        let index = self.keys.iter().position(|k| k.id == id)?;
+        Some(self.keys.remove(index))
+    }
+
+    pub fn key(&self, id: KeyId) -> Option<&KeyFrame<V>> {
+        self.keys.iter().find(|k| k.id == id)
+    }
+
+    pub fn key_at(&self, time: Time) -> Option<&KeyFrame<V>> {
+        self.keys
+            .binary_search_by(|k| k.time.cmp(&time))
+            .ok()
+            .map(|i| &self.keys[i])
+    }
+
+    /// Moves a key to `time`, `false` if another key is already there.
+    pub fn set_key_time(&mut self, id: KeyId, time: Time) -> bool {
+        if self.keys.iter().any(|k| k.time == time && k.id != id) {
+            return false;
+        }
+        let Some(mut key) = self.remove_key(id) else {
+            return false;
+        };
+        key.time = time;
+        let index = self.keys.partition_point(|k| k.time <= time);
+        self.keys.insert(index, key);
+        true
+    }
+
+    pub fn set_key_value(&mut self, id: KeyId, value: V) -> bool {
+        match self.keys.iter_mut().find(|k| k.id == id) {
+            Some(key) => {
+                key.value = value;
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn set_key_interpolation(&mut self, id: KeyId, interpolation: Interpolation) -> bool {
+        match self.keys.iter_mut().find(|k| k.id == id) {
+            Some(key) => {
+                key.interpolation = interpolation;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// The interpolated value at `time`, held constant before the first and after the last key.
+    pub fn evaluate(&self, time: Time) -> Option<V> {
+        let index = self.keys.partition_point(|k| k.time <= time);
+        let Some(from) = index.checked_sub(1).map(|i| &self.keys[i]) else {
+            return self.keys.first().map(|k| k.value.clone());
+        };
+        let Some(to) = self.keys.get(index) else {
+            return Some(from.value.clone());
+        };
+
+        let span = (to.time - from.time).ticks();
+        let t = if span > 0 {
+            ((time - from.time).ticks() as f64 / span as f64) as f32
+        } else {
+            1.0
+        };
+        Some(from.value.lerp(&to.value, from.interpolation.ease(t)))
+    }
+
+    pub fn clear_keys(&mut self) {
+        self.keys.clear();
+    }
+}
+
+/// Saved form of a [`KeyFrameTrack`], key ids are not kept.
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+struct KeyFrameTrackData {
+    name: String,
+    default_interpolation: Interpolation,
+    keys: Vec<KeyFrameData>,
+}
+
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+struct KeyFrameData {
+    time: Time,
+    value: TrackData,
+    interpolation: Interpolation,
+}
+
+#[cfg(feature = "serde")]
+impl<V: KeyValue> KeyFrameTrack<V> {
+    /// Tag of keyframe tracks holding `V` in saved documents.
+    pub fn type_tag() -> String {
+        format!("keyframe:{}", V::type_name())
+    }
+
+    /// Recreates a track from [`Track::save`] data, `None` if the data or a value is invalid.
+    pub fn load(data: TrackData) -> Option<Self> {
+        let data: KeyFrameTrackData = data.deserialize_into().ok()?;
+        let mut track = Self::new(data.name).with_default_interpolation(data.default_interpolation);
+        for key in data.keys {
+            track.insert_key_with(key.time, V::from_data(key.value)?, key.interpolation);
+        }
+        Some(track)
+    }
+}
+
+impl<V: KeyValue> Track for KeyFrameTrack<V> {
+    fn draw_head(&mut self, ui: &imgui::Ui) {
+        draw_track_name(ui, &self.name, Self::TRACK_HEIGHT);
+    }
+
+    fn head_config(&mut self) -> (String, f32) {
+        (
+            format!("keyframe_track_head_{}", self.name),
+            Self::TRACK_HEIGHT,
+        )
+    }
+
+    fn draw_lane(&mut self, _ui: &imgui::Ui, view: &TrackView) {
+        let center_y = (view.lane_min.y + view.lane_max.y) / 2.0;
+        let size = Self::KEY_SIZE;
+
+        for key in &self.keys {
+            if key.time < view.left_time || key.time > view.right_time {
+                continue;
+            }
+            let x = view.time_to_x(key.time);
+            let color = if view.is_selected(key.id) {
+                [1.0, 0.85, 0.6]
+            } else {
+                [0.86, 0.554, 0.33]
+            };
+
+            // The shape tells the interpolation towards the next key apart.
+            match key.interpolation {
+                Interpolation::Constant => {
+                    view.draw_list
+                        .add_rect(
+                            [x - size * 0.8, center_y - size * 0.8],
+                            [x + size * 0.8, center_y + size * 0.8],
+                            color,
+                        )
+                        .filled(true)
+                        .build();
+                }
+                Interpolation::Linear => {
+                    view.draw_list
+                        .add_polyline(
+                            vec![
+                                [x, center_y - size],
+                                [x + size, center_y],
+                                [x, center_y + size],
+                                [x - size, center_y],
+                            ],
+                            color,
+                        )
+                        .filled(true)
+                        .build();
+                }
+                Interpolation::Bezier { .. } => {
+                    view.draw_list
+                        .add_circle([x, center_y], size * 0.9, color)
+                        .filled(true)
+                        .build();
+                }
+            }
+        }
+    }
+
+    fn key_times(&self) -> Vec<(KeyId, Time)> {
+        self.keys.iter().map(|k| (k.id, k.time)).collect()
+    }
+
+    fn add_key_at(&mut self, time: Time) -> Option<KeyId> {
+        let value = self.evaluate(time).unwrap_or_else(V::zero);
+        Some(self.insert_key(time, value))
+    }
+
+    fn move_key(&mut self, key: KeyId, time: Time) -> bool {
+        self.set_key_time(key, time)
+    }
+
+    fn delete_key(&mut self, key: KeyId) -> bool {
+        self.remove_key(key).is_some()
+    }
+
+    fn clipboard_type(&self) -> Option<&str> {
+        Some(V::type_name())
+    }
+
+    fn copy_key(&self, key: KeyId) -> Option<String> {
+        let key = self.key(key)?;
+        Some(format!(
+            "{}\t{}",
+            key.interpolation.to_text(),
+            key.value.to_text()?
+        ))
+    }
+
+    fn paste_key(&mut self, time: Time, text: &str) -> Result<KeyId, PasteError> {
+        let invalid = || PasteError::InvalidKey(text.to_string());
+        let (interpolation, value) = text.split_once('\t').ok_or_else(invalid)?;
+        let value = V::from_text(value).ok_or_else(invalid)?;
+        let interpolation = Interpolation::from_text(interpolation).ok_or_else(invalid)?;
+        Ok(self.insert_key_with(time, value, interpolation))
+    }
+
+    fn key_state(&self, key: KeyId) -> Option<KeyState> {
+        Some(Rc::new(self.key(key)?.clone()))
+    }
+
+    fn restore_key(&mut self, state: &dyn Any) -> bool {
+        let Some(key) = state.downcast_ref::<KeyFrame<V>>() else {
+            return false;
+        };
+        self.remove_key(key.id);
+        let index = self.keys.partition_point(|k| k.time <= key.time);
+        self.keys.insert(index, key.clone());
+        self.next_key_id = self.next_key_id.max(key.id.0 + 1);
+        true
+    }
+
+    #[cfg(feature = "serde")]
+    fn type_tag(&self) -> Option<String> {
+        Some(Self::type_tag())
+    }
+
+    #[cfg(feature = "serde")]
+    fn save(&self) -> Option<TrackData> {
+        let keys = self
+            .keys
+            .iter()
+            .map(|key| {
+                Some(KeyFrameData {
+                    time: key.time,
+                    value: key.value.to_data()?,
+                    interpolation: key.interpolation,
+                })
+            })
+            .collect::<Option<_>>()?;
+        serde_value::to_value(KeyFrameTrackData {
+            name: self.name.clone(),
+            default_interpolation: self.default_interpolation,
+            keys,
+        })
+        .ok()
+    }
+
+    fn sample(&self, time: Time) -> Option<Value> {
+        self.evaluate(time).map(|v| v.to_value())
+    }
+
+    fn name(&self) -> Option<&str> {
+        Some(&self.name)
+    }
+
+    fn curve_count(&self) -> usize {
+        V::zero().curve_values().len()
+    }
+
+    fn key_curve_value(&self, key: KeyId, curve: usize) -> Option<f32> {
+        self.key(key)?.value.curve_values().get(curve).copied()
+    }
+
+    fn set_key_curve_value(&mut self, key: KeyId, curve: usize, value: f32) -> bool {
+        let Some(value) = self
+            .key(key)
+            .and_then(|k| k.value.with_curve_value(curve, value))
+        else {
+            return false;
+        };
+        self.set_key_value(key, value)
+    }
+
+    fn sample_curve(&self, time: Time, curve: usize) -> Option<f32> {
+        self.evaluate(time)?.curve_values().get(curve).copied()
+    }
+
+    fn key_interpolation(&self, key: KeyId) -> Option<Interpolation> {
+        self.key(key).map(|k| k.interpolation)
+    }
+
+    fn set_key_interpolation(&mut self, key: KeyId, interpolation: Interpolation) -> bool {
+        KeyFrameTrack::set_key_interpolation(self, key, interpolation)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Timeline;
+
+    fn ms(millis: i64) -> Time {
+        Time::from_millis(millis)
+    }
+
+    fn track(interpolation: Interpolation) -> KeyFrameTrack<f32> {
+        let mut track = KeyFrameTrack::new("test").with_default_interpolation(interpolation);
+        track.insert_key(ms(1000), 0.0);
+        track.insert_key(ms(2000), 10.0);
+        track
+    }
+
+    #[test]
+    fn holds_the_first_and_last_value() {
+        let track = track(Interpolation::Linear);
+        assert_eq!(track.evaluate(ms(0)), Some(0.0));
+        assert_eq!(track.evaluate(ms(3000)), Some(10.0));
+        assert_eq!(KeyFrameTrack::<f32>::new("empty").evaluate(ms(0)), None);
+    }
+
+    #[test]
+    fn interpolates_between_keys() {
+        assert_eq!(track(Interpolation::Linear).evaluate(ms(1250)), Some(2.5));
+        assert_eq!(track(Interpolation::Constant).evaluate(ms(1999)), Some(0.0));
+        assert_eq!(
+            track(Interpolation::Constant).evaluate(ms(2000)),
+            Some(10.0)
+        );
+
+        let eased = track(Interpolation::EASE_IN_OUT);
+        assert_eq!(
+            eased.evaluate(ms(1500)).map(|v| (v * 100.0).round()),
+            Some(500.0)
+        );
+        assert!(eased.evaluate(ms(1100)).unwrap() < 1.0);
+        assert!(eased.evaluate(ms(1900)).unwrap() > 9.0);
+    }
+
+    #[test]
+    fn refuses_to_move_a_key_onto_another() {
+        let mut track = track(Interpolation::Linear);
+        let [first, second] = [0, 1].map(|i| track.keys()[i].id());
+        assert!(!track.set_key_time(first, ms(2000)));
+        assert!(track.set_key_time(second, ms(2000)));
+        assert!(track.set_key_time(first, ms(3000)));
+        let times = track.keys().iter().map(|k| k.time()).collect::<Vec<_>>();
+        assert_eq!(times, [ms(2000), ms(3000)]);
+    }
+
+    #[test]
+    fn samples_tracks_without_drawing() {
+        let mut timeline = Timeline::new("test");
+        let floats = timeline.add_track(track(Interpolation::Linear));
+        let mut colors = KeyFrameTrack::<[f32; 3]>::new("colors");
+        colors.insert_key(ms(0), [0.0, 0.0, 0.0]);
+        colors.insert_key(ms(2000), [1.0, 0.5, 0.0]);
+        let colors = timeline.add_track(colors);
+        timeline.add_track(KeyFrameTrack::<f32>::new("empty"));
+
+        let samples = timeline.sample_all(ms(1500));
+        assert_eq!(samples.len(), 2);
+        assert!(matches!(samples[0], (id, Value::Float(v)) if id == floats && v == 5.0));
+        assert!(
+            matches!(samples[1], (id, Value::Rgb(v)) if id == colors && v == [0.75, 0.375, 0.0])
+        );
+        assert!(matches!(
+            timeline.sample(floats, ms(0)),
+            Some(Value::Float(v)) if v == 0.0
+        ));
+    }
+}
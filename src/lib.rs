@@ -1,5 +1,4 @@
-use chrono::{Duration, NaiveTime};
-use imgui::{sys::ImGuiKey_Space, Drag, StyleColor, StyleVar};
+use std::any::Any;
 
 const DEBUG_DRAW: bool = false;
 macro_rules! debug {
@@ -10,6 +9,44 @@ macro_rules! debug {
     };
 }
 
+mod clip;
+mod clipboard;
+#[cfg(feature = "serde")]
+mod document;
+mod event;
+mod event_track;
+mod graph;
+mod keyframe;
+mod playback;
+#[cfg(feature = "serde")]
+mod registry;
+mod snap;
+mod time;
+mod undo;
+mod view;
+
+pub use clip::{Clip, ClipTrack};
+pub use clipboard::PasteError;
+#[cfg(feature = "serde")]
+pub use document::{
+    to_track_data, DocumentError, TimelineDocument, TrackData, TrackDocument, ViewState,
+    DOCUMENT_VERSION,
+};
+pub use event::{Response, TimelineEvent};
+pub use event_track::{EventTrack, TrackEvent};
+pub use graph::EditorMode;
+pub use keyframe::{Interpolation, KeyFrame, KeyFrameTrack, KeyValue, Value};
+pub use playback::{CrossedEvent, PlaybackMode};
+#[cfg(feature = "serde")]
+pub use registry::TrackRegistry;
+pub use snap::{Marker, SnapSettings};
+pub use time::{FrameRate, ParseTimeError, Time, TimeFormat};
+pub use undo::{Command, KeyState, UndoHost};
+
+use graph::GraphView;
+use undo::History;
+use view::{BoxSelect, KeyDrag, KeyTrim, RulerScale, TimeEdit};
+
 pub fn hello(ui: &imgui::Ui) {
     if let Some(_token) = ui.window("imgui_timeline_rs").begin() {
         ui.text("imgui_timeline_rs Version: 0.0.1");
@@ -21,13 +58,47 @@ pub type Point = cgmath::Vector2<f32>;
 pub struct Timeline {
     window_ident: String,
     name: String,
-    time: chrono::NaiveTime,
-    left_time: chrono::NaiveTime,
-    tracks: Vec<Box<dyn Track>>,
+    time: Time,
+    left_time: Time,
+    duration: Time,
+    frame_rate: FrameRate,
+    snap: SnapSettings,
+    markers: Vec<Marker>,
+    time_format: TimeFormat,
+    tracks: Vec<TrackEntry>,
+    next_track_id: u64,
 
     time_scale: f32,
     playback_speed: f32,
     playing: bool,
+    scrubbing: bool,
+    /// Where the last tick left the playhead, events there have been reported.
+    last_tick_time: Option<Time>,
+    playback_mode: PlaybackMode,
+    in_point: Option<Time>,
+    out_point: Option<Time>,
+
+    selection: Vec<KeyRef>,
+    key_drag: Option<KeyDrag>,
+    key_trim: Option<KeyTrim>,
+    box_select: Option<BoxSelect>,
+    track_drag: Option<TrackId>,
+    time_edit: Option<TimeEdit>,
+    events: Vec<TimelineEvent>,
+    history: History,
+    ruler_menu_time: Time,
+    /// Target of the snap made this frame, drawn as a guide line.
+    snap_guide: Option<Time>,
+    ruler_scale: RulerScale,
+    editor_mode: EditorMode,
+    graph: GraphView,
+
+    lane_x: f32,
+    lane_width: f32,
+    track_scroll_y: f32,
+    /// Vertical scroll applied to the track table on the next draw.
+    pending_scroll_y: Option<f32>,
+    panning: bool,
 }
 
 impl Timeline {
@@ -37,383 +108,413 @@ impl Timeline {
             window_ident: format!("{0}##imgui_timeline_rs_timeline_{0}", name),
             name,
             time_scale: 0.4,
-            time: NaiveTime::parse_from_str("00:00:00.0", "%H:%M:%S%.f").expect("WHy!!!!"),
-            left_time: NaiveTime::parse_from_str("00:00:02.0", "%H:%M:%S%.f").expect("WHy!!!!"),
+            time: Time::ZERO,
+            left_time: Time::from_millis(2000),
+            duration: Time::from_millis(30_000),
+            frame_rate: FrameRate::default(),
+            snap: SnapSettings::default(),
+            markers: Vec::new(),
+            time_format: TimeFormat::default(),
             playback_speed: 1.0,
-            tracks: {
-                (0..20)
-                    .map(|i| {
-                        Box::new(KeyFrameTrack {
-                            name: format!("Test Track {}", i),
-                        }) as Box<dyn Track>
-                    })
-                    .collect::<Vec<_>>()
-            },
+            tracks: Vec::new(),
+            next_track_id: 0,
             playing: false,
+            scrubbing: false,
+            last_tick_time: None,
+            playback_mode: PlaybackMode::default(),
+            in_point: None,
+            out_point: None,
+            selection: Vec::new(),
+            key_drag: None,
+            key_trim: None,
+            box_select: None,
+            track_drag: None,
+            time_edit: None,
+            events: Vec::new(),
+            history: History::default(),
+            ruler_menu_time: Time::ZERO,
+            snap_guide: None,
+            ruler_scale: RulerScale::default(),
+            editor_mode: EditorMode::default(),
+            graph: GraphView::default(),
+            lane_x: 0.0,
+            lane_width: 0.0,
+            track_scroll_y: 0.0,
+            pending_scroll_y: None,
+            panning: false,
         }
     }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
 }
 
 impl Timeline {
-    pub fn draw(&mut self, ui: &imgui::Ui, dt: f32) {
-        if self.playing {
-            self.time = self
-                .time
-                .overflowing_add_signed(Duration::milliseconds(
-                    (dt * self.playback_speed * 1000.0) as i64,
-                ))
-                .0;
+    pub fn time(&self) -> Time {
+        self.time
+    }
+
+    pub fn set_time(&mut self, time: Time) {
+        self.time = time;
+    }
+
+    pub fn frame_rate(&self) -> FrameRate {
+        self.frame_rate
+    }
+
+    pub fn set_frame_rate(&mut self, frame_rate: FrameRate) {
+        self.frame_rate = frame_rate;
+    }
+
+    /// Whether the playhead and edited keys snap to frame boundaries, see
+    /// [`Timeline::set_snap_settings`] for other snap targets.
+    pub fn snap_to_frames(&self) -> bool {
+        self.snap.frames
+    }
+
+    pub fn set_snap_to_frames(&mut self, snap: bool) {
+        self.snap.frames = snap;
+    }
+
+    /// How the time cell shows the playhead time and parses typed times.
+    pub fn time_format(&self) -> TimeFormat {
+        self.time_format
+    }
+
+    pub fn set_time_format(&mut self, format: TimeFormat) {
+        self.time_format = format;
+    }
+
+    /// Rounds `time` to the closest frame if frame snapping is enabled.
+    pub fn snap_time(&self, time: Time) -> Time {
+        if self.snap.frames {
+            time.round_to_frame(self.frame_rate)
+        } else {
+            time
         }
+    }
 
-        let color_frame_bg = ui.style_color(StyleColor::FrameBg);
-        let color_border = ui.style_color(StyleColor::Border);
+    /// The frame the playhead is in.
+    pub fn current_frame(&self) -> i64 {
+        self.time.frame(self.frame_rate)
+    }
+
+    /// Where the playhead is shown, the start of the current frame while snapping.
+    pub fn display_time(&self) -> Time {
+        if self.snap.frames {
+            self.time.floor_to_frame(self.frame_rate)
+        } else {
+            self.time
+        }
+    }
 
-        let window_padding = ui.push_style_var(StyleVar::WindowPadding([0.0, 0.0]));
-        let frame_border_size = ui.push_style_var(StyleVar::FrameBorderSize(0.0));
+    /// Length of the timeline, the horizontal scrollbar covers at least this range.
+    pub fn duration(&self) -> Time {
+        self.duration
+    }
 
-        if let Some(_token) = ui
-            .window(&self.window_ident)
-            .scroll_bar(false)
-            .scrollable(false)
-            .size([200.0, 200.0], imgui::Condition::FirstUseEver)
-            .size_constraints([200.0, 200.0], [f32::INFINITY; 2])
-            .begin()
-        {
-            let wdl = ui.get_window_draw_list();
-            let top_left: Point = ui.window_pos().into();
-            let focused =
-                ui.is_window_focused_with_flags(imgui::WindowFocusedFlags::ROOT_AND_CHILD_WINDOWS);
+    pub fn set_duration(&mut self, duration: Time) {
+        self.duration = duration;
+    }
 
-            let height = ui.text_line_height_with_spacing();
-            let item_spacing = ui.push_style_var(StyleVar::ItemSpacing([4.0, 0.0]));
+    /// The end of the timeline, or of the last key if it lies beyond.
+    pub fn content_end(&self) -> Time {
+        self.tracks
+            .iter()
+            .flat_map(|e| e.track.key_times())
+            .map(|(_, time)| time)
+            .fold(self.duration, Time::max)
+    }
 
-            self.draw_toolbar(ui, height, &wdl, color_frame_bg, color_border);
+    /// Samples a single track, `None` if the track is unknown or has nothing to sample.
+    pub fn sample(&self, id: TrackId, time: Time) -> Option<Value> {
+        self.track(id)?.sample(time)
+    }
+
+    /// Samples every track that has a value at `time`, in display order.
+    pub fn sample_all(&self, time: Time) -> Vec<(TrackId, Value)> {
+        self.tracks
+            .iter()
+            .filter_map(|e| Some((e.id, e.track.sample(time)?)))
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct TrackId(u64);
+
+struct TrackEntry {
+    id: TrackId,
+    track: Box<dyn Track>,
+}
+
+impl Timeline {
+    /// Appends a track to the end of the timeline.
+    pub fn add_track(&mut self, track: impl Track + 'static) -> TrackId {
+        self.insert_track(self.tracks.len(), track)
+    }
 
-            self.draw_editor(ui, height, &wdl, focused);
+    /// Inserts a track at `index`, clamped to the number of tracks.
+    pub fn insert_track(&mut self, index: usize, track: impl Track + 'static) -> TrackId {
+        let id = TrackId(self.next_track_id);
+        self.next_track_id += 1;
+        let index = index.min(self.tracks.len());
+        self.tracks.insert(
+            index,
+            TrackEntry {
+                id,
+                track: Box::new(track),
+            },
+        );
+        self.record_add_track(id, index);
+        id
+    }
 
-            self.draw_footer(ui, wdl, color_border);
+    /// Removes the track. With [`Timeline::set_undo_track_edits`] the history keeps it so the
+    /// removal can be undone.
+    pub fn remove_track(&mut self, id: TrackId) -> bool {
+        let Some(index) = self.track_index(id) else {
+            return false;
+        };
+        self.selection.retain(|r| r.track != id);
+        let entry = self.tracks.remove(index);
+        self.record_remove_track(id, index, entry.track);
+        true
+    }
 
-            item_spacing.pop();
+    /// Moves the track to `new_index`, returns false if the id is unknown.
+    pub fn move_track(&mut self, id: TrackId, new_index: usize) -> bool {
+        let Some(index) = self.track_index(id) else {
+            return false;
+        };
+        let entry = self.tracks.remove(index);
+        let new_index = new_index.min(self.tracks.len());
+        self.tracks.insert(new_index, entry);
+        if new_index != index {
+            self.record_move_track(id, index, new_index);
         }
-        frame_border_size.pop();
-        window_padding.pop();
-    }
-
-    fn draw_editor(
-        &mut self,
-        ui: &imgui::Ui,
-        height: f32,
-        wdl: &imgui::DrawListMut<'_>,
-        focused: bool,
-    ) {
-        const ITEM_SPACING: f32 = 2.0;
-        if let Some(_child) = ui
-            .child_window("editor")
-            .scroll_bar(false)
-            .scrollable(false)
-            .size([0.0, -(height + ITEM_SPACING)])
-            .begin()
-        {
-            let top_left: Point = ui.window_pos().into();
-            let region_max: Point = ui.content_region_max().into();
-            debug! {
-                        wdl.add_rect(
-                            (top_left + Point::new(1.0, 1.0)).array(),
-                            (top_left + region_max - Point::new(1.0, 1.0)).array(),
-                            [0.0, 1.0, 0.0],
-                        )
-                        .thickness(1.0)
-                        .build();
-            }
-            // ui.text("Editor")
-
-            let cell_padding = ui.push_style_var(StyleVar::CellPadding([0.0; 2]));
-            if let Some(_table) = ui.begin_table_with_flags(
-                "track_table",
-                2,
-                imgui::TableFlags::PRECISE_WIDTHS
-                    | imgui::TableFlags::RESIZABLE
-                    | imgui::TableFlags::BORDERS_H
-                    | imgui::TableFlags::BORDERS_V
-                    | imgui::TableFlags::SCROLL_Y,
-            ) {
-                if focused && ui.is_key_index_pressed_no_repeat(ImGuiKey_Space) {
-                    self.playing = !self.playing;
-                }
+        true
+    }
 
-                ui.table_setup_scroll_freeze(2, 1);
-                ui.table_setup_column("##0");
-                ui.table_setup_column("##1");
-
-                const FIRST_HEIGHT: f32 = 35.0;
-                ui.table_next_row();
-                ui.table_set_column_index(0);
-                ui.set_window_font_scale(1.25); //TODO: Replace with bigger font.
-                let time = self.time.format("%H:%M:%S%.3f").to_string();
-                let size: Point = ui.calc_text_size(&time).into();
-                let max: Point = ui.content_region_max().into();
-                ui.set_cursor_pos([
-                    (max.x - size.x) / 2.0,
-                    ui.cursor_pos()[1] + (FIRST_HEIGHT - ui.text_line_height_with_spacing()) / 2.0,
-                ]);
-                ui.text(time);
-                ui.set_window_font_scale(1.0);
-
-                wdl.add_line(
-                    [top_left.x, top_left.y + FIRST_HEIGHT],
-                    [top_left.x + region_max.x, top_left.y + FIRST_HEIGHT],
-                    [7.0, 7.0, 7.0],
-                )
-                .thickness(1.5)
-                .build();
-
-                ui.table_set_column_index(1);
-
-                if let Some(_t) = ui
-                    .child_window("timeline")
-                    .scroll_bar(false)
-                    .scrollable(false)
-                    .size([0.0, FIRST_HEIGHT])
-                    .begin()
-                {
-                    let top_left: Point = ui.window_pos().into();
-                    let outer_region_max = region_max;
-                    let region_max: Point = ui.content_region_max().into();
-
-                    const SECOND_WIDTH: f32 = 250.0;
-                    let second_width = SECOND_WIDTH * self.time_scale;
-
-                    //Input
-                    if focused && ui.is_window_hovered() {
-                        if ui.is_mouse_clicked(imgui::MouseButton::Left) {
-                            let mut mouse_pos = ui.io().mouse_pos[0];
-                            mouse_pos -= top_left.x;
-                            mouse_pos /= second_width;
-                            self.time = self
-                                .left_time
-                                .overflowing_add_signed(Duration::milliseconds(
-                                    (mouse_pos * 1000.0) as i64,
-                                ))
-                                .0;
-                        }
-                    }
-                    debug! {
-                                wdl.add_rect(
-                                    (top_left + Point::new(1.0, 1.0)).array(),
-                                    (top_left + region_max - Point::new(1.0, 1.0)).array(),
-                                    [1.0, 1.0, 0.0],
-                                )
-                                .thickness(1.0)
-                                .build();
-                    }
-
-                    let width = region_max.x;
-                    wdl.with_clip_rect(top_left.array(), (top_left + region_max).array(), || {
-                        ui.set_window_font_scale(0.9); //TODO: Replace with smaller font
-                        let mut x = 0.0;
-                        while x <= width {
-                            wdl.add_line(
-                                [top_left.x + x, top_left.y + region_max.y],
-                                [top_left.x + x, top_left.y + region_max.y * 0.333],
-                                [1.0; 3],
-                            )
-                            .thickness(1.0)
-                            .build();
-
-                            let between_steps = 10;
-                            for i in 1..between_steps {
-                                wdl.add_line(
-                                    [
-                                        top_left.x
-                                            + x
-                                            + second_width / between_steps as f32 * i as f32,
-                                        top_left.y + region_max.y,
-                                    ],
-                                    [
-                                        top_left.x
-                                            + x
-                                            + second_width / between_steps as f32 * i as f32,
-                                        top_left.y + region_max.y * 0.666,
-                                    ],
-                                    [0.5; 3],
-                                )
-                                .thickness(1.0)
-                                .build();
-                            }
-
-                            wdl.add_text(
-                                [top_left.x + x + 5.0, top_left.y + region_max.y * 0.1],
-                                [1.0; 3],
-                                (self
-                                    .left_time
-                                    .overflowing_add_signed(Duration::milliseconds(
-                                        (x / second_width * 1000.0) as i64,
-                                    )))
-                                .0
-                                .format("%H:%M:%S")
-                                .to_string(),
-                            );
-                            x += second_width;
-                        }
-                        ui.set_window_font_scale(1.0);
-                    });
-
-                    self.draw_track_head(top_left, second_width, wdl, region_max, outer_region_max);
-                }
+    /// Adds a key to the track through [`Track::add_key_at`].
+    pub fn add_key(&mut self, track: TrackId, time: Time) -> Option<KeyRef> {
+        let existing = self.key_state_at(track, time);
+        let key = KeyRef::new(track, self.track_mut(track)?.add_key_at(time)?);
+        let replaced = existing
+            .filter(|(k, _)| *k == key.key)
+            .map(|(_, state)| state);
+        self.record_add_key(key, replaced);
+        Some(key)
+    }
 
-                for track in &mut self.tracks {
-                    ui.table_next_row();
-                    ui.table_set_column_index(0);
-                    draw_track(track, ui);
-                }
-            }
+    /// The key at `time` with its state, tracks may overwrite it when creating a key there.
+    fn key_state_at(&self, track: TrackId, time: Time) -> Option<(KeyId, KeyState)> {
+        let track = self.track(track)?;
+        let (key, _) = track.key_times().into_iter().find(|(_, t)| *t == time)?;
+        Some((key, track.key_state(key)?))
+    }
 
-            cell_padding.pop();
+    pub fn move_key(&mut self, key: KeyRef, time: Time) -> bool {
+        let Some(track) = self.track_mut(key.track) else {
+            return false;
+        };
+        let Some((_, from)) = track.key_times().into_iter().find(|(k, _)| *k == key.key) else {
+            return false;
+        };
+        // Lasting keys may refuse moves onto each other, so undoing a move of several of
+        // them restores their states instead of replaying the moves in some other order.
+        let before = track
+            .key_end(key.key)
+            .and_then(|_| track.key_state(key.key));
+        if !track.move_key(key.key, time) {
+            return false;
+        }
+        match before.zip(track.key_state(key.key)) {
+            Some((before, after)) => self.record_change_key(key, before, after),
+            None => self.record_move_key(key, from, time),
         }
+        true
     }
 
-    fn draw_track_head(
-        &mut self,
-        top_left: cgmath::Vector2<f32>,
-        second_width: f32,
-        wdl: &imgui::DrawListMut<'_>,
-        region_max: cgmath::Vector2<f32>,
-        outer_region_max: cgmath::Vector2<f32>,
-    ) {
-        let track_head_center = top_left.x
-            + ((self.time - self.left_time).num_milliseconds() as f32 / 1000.0) * second_width;
-
-        if track_head_center >= top_left.x {
-            wdl.add_polyline(
-                vec![
-                    [track_head_center, top_left.y + region_max.y],
-                    [track_head_center - 5.0, top_left.y + region_max.y * 0.8],
-                    [track_head_center - 5.0, top_left.y + region_max.y * 0.6],
-                    [track_head_center + 5.0, top_left.y + region_max.y * 0.6],
-                    [track_head_center + 5.0, top_left.y + region_max.y * 0.8],
-                ],
-                [1.0, 0.0, 0.0],
-            )
-            .filled(true)
-            .thickness(2.0)
-            .build();
-
-            wdl.add_line(
-                [track_head_center, top_left.y + region_max.y * 0.7],
-                [track_head_center, top_left.y + outer_region_max.y],
-                [1.0, 0.0, 0.0],
-            )
-            .thickness(2.0)
-            .build();
+    /// Deletes the key and deselects it.
+    pub fn delete_key(&mut self, key: KeyRef) -> bool {
+        self.deselect_key(key);
+        let Some(track) = self.track_mut(key.track) else {
+            return false;
+        };
+        let state = track.key_state(key.key);
+        if !track.delete_key(key.key) {
+            return false;
+        }
+        if let Some(state) = state {
+            self.record_delete_key(key, state);
         }
+        true
     }
 
-    fn draw_toolbar(
-        &mut self,
-        ui: &imgui::Ui,
-        height: f32,
-        wdl: &imgui::DrawListMut<'_>,
-        color_frame_bg: [f32; 4],
-        color_border: [f32; 4],
-    ) {
-        if let Some(_child) = ui
-            .child_window("toolbar")
-            .scroll_bar(false)
-            .scrollable(false)
-            .size([0.0, height])
-            .begin()
-        {
-            let top_left: Point = ui.window_pos().into();
-            let region_max: Point = ui.content_region_max().into();
-            debug! {
-                        wdl.add_rect(
-                            (top_left + Point::new(1.0, 1.0)).array(),
-                            (top_left + region_max - Point::new(1.0, 1.0)).array(),
-                            [1.0, 0.0, 0.0],
-                        )
-                        .thickness(1.0)
-                        .build();
-            };
-
-            //BG
-            wdl.add_rect(
-                top_left.array(),
-                (top_left + region_max).array(),
-                color_frame_bg,
-            )
-            .filled(true)
-            .build();
-            wdl.add_line(
-                [top_left.x, top_left.y + region_max.y],
-                [top_left.x + region_max.x, top_left.y + region_max.y],
-                color_border,
-            )
-            .thickness(1.0)
-            .build();
-
-            ui.set_cursor_pos([2.0, (region_max.y - height) / 2.0 + 2.0]);
-
-            // Toolbar Text
-            ui.text("Toolbar: ");
-            ui.same_line();
-            // ui.button_with_size("Test", [0.0, height - 4.0]);
-            // ui.same_line();
-
-            //Scale Input
-            ui.text("Scale:");
-            ui.same_line();
-            ui.set_next_item_width(50.0);
-            Drag::new("##scale")
-                .speed(0.05)
-                .range(0.01, 10.0)
-                .display_format("%.2f")
-                .build(ui, &mut self.time_scale);
-
-            ui.same_line();
-
-            //SPeed Input
-            ui.text("Speed:");
-            ui.same_line();
-            ui.set_next_item_width(50.0);
-            Drag::new("##speed")
-                .speed(0.01)
-                .range(0.1, 5.0)
-                .display_format("%.2f")
-                .build(ui, &mut self.playback_speed);
+    /// Trims a lasting key, see [`Track::key_end`].
+    pub fn set_key_range(&mut self, key: KeyRef, start: Time, end: Time) -> bool {
+        let Some(track) = self.track_mut(key.track) else {
+            return false;
         };
+        let before = track.key_state(key.key);
+        if !track.set_key_range(key.key, start, end) {
+            return false;
+        }
+        let after = track.key_state(key.key);
+        if let (Some(before), Some(after)) = (before, after) {
+            self.record_change_key(key, before, after);
+        }
+        true
+    }
+
+    /// Cuts a lasting key in two at `time` and returns the second part.
+    pub fn split_key(&mut self, key: KeyRef, time: Time) -> Option<KeyRef> {
+        let track = self.track_mut(key.track)?;
+        let before = track.key_state(key.key);
+        let second = KeyRef::new(key.track, track.split_key(key.key, time)?);
+        let after = track.key_state(key.key);
+        self.begin_group();
+        if let (Some(before), Some(after)) = (before, after) {
+            self.record_change_key(key, before, after);
+        }
+        self.record_add_key(second, None);
+        self.end_group();
+        Some(second)
     }
 
-    fn draw_footer(&mut self, ui: &imgui::Ui, wdl: imgui::DrawListMut<'_>, color_border: [f32; 4]) {
-        if let Some(_child) = ui
-            .child_window("footer")
-            .scroll_bar(false)
-            .scrollable(false)
-            .size([0.0, 0.0])
-            .begin()
-        {
-            let top_left: Point = ui.window_pos().into();
-            let region_max: Point = ui.content_region_max().into();
-            debug! {
-                        wdl.add_rect(
-                            (top_left + Point::new(1.0, 1.0)).array(),
-                            (top_left + region_max - Point::new(1.0, 1.0)).array(),
-                            [0.0, 0.0, 1.0],
-                        )
-                        .thickness(1.0)
-                        .build();
+    /// Splits the selected lasting keys under the playhead, or all of them if none of the
+    /// selected keys is under it. Returns each split key with its second part.
+    pub fn split_at_playhead(&mut self) -> Vec<(KeyRef, KeyRef)> {
+        let time = self.display_time();
+        let mut under_playhead = Vec::new();
+        for entry in &self.tracks {
+            for (key, start) in entry.track.key_times() {
+                if entry
+                    .track
+                    .key_end(key)
+                    .is_some_and(|end| start < time && time < end)
+                {
+                    under_playhead.push(KeyRef::new(entry.id, key));
+                }
             }
+        }
+        if under_playhead.iter().any(|key| self.is_key_selected(*key)) {
+            under_playhead.retain(|key| self.is_key_selected(*key));
+        }
+
+        self.begin_group();
+        let split = under_playhead
+            .into_iter()
+            .filter_map(|key| Some((key, self.split_key(key, time)?)))
+            .collect();
+        self.end_group();
+        split
+    }
+
+    pub fn track_index(&self, id: TrackId) -> Option<usize> {
+        self.tracks.iter().position(|e| e.id == id)
+    }
+
+    pub fn track(&self, id: TrackId) -> Option<&dyn Track> {
+        self.tracks
+            .iter()
+            .find(|e| e.id == id)
+            .map(|e| e.track.as_ref())
+    }
+
+    pub fn track_mut(&mut self, id: TrackId) -> Option<&mut (dyn Track + 'static)> {
+        self.tracks
+            .iter_mut()
+            .find(|e| e.id == id)
+            .map(|e| e.track.as_mut())
+    }
+
+    /// Typed access to a track, `None` if the id is unknown or the track is of another type.
+    pub fn track_as<T: Track>(&self, id: TrackId) -> Option<&T> {
+        self.track(id)?.as_any().downcast_ref()
+    }
 
-            wdl.add_line(
-                [top_left.x, top_left.y],
-                [top_left.x + region_max.x, top_left.y],
-                color_border,
-            )
-            .thickness(1.0)
-            .build();
+    pub fn track_as_mut<T: Track>(&mut self, id: TrackId) -> Option<&mut T> {
+        self.track_mut(id)?.as_any_mut().downcast_mut()
+    }
+
+    pub fn track_count(&self) -> usize {
+        self.tracks.len()
+    }
+
+    /// Iterates the tracks in display order.
+    pub fn tracks(&self) -> impl Iterator<Item = (TrackId, &dyn Track)> {
+        self.tracks.iter().map(|e| (e.id, e.track.as_ref()))
+    }
+
+    pub fn tracks_mut(&mut self) -> impl Iterator<Item = (TrackId, &mut (dyn Track + 'static))> {
+        self.tracks.iter_mut().map(|e| (e.id, e.track.as_mut()))
+    }
+}
+
+/// A key on a specific track of the timeline.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct KeyRef {
+    pub track: TrackId,
+    pub key: KeyId,
+}
+
+impl KeyRef {
+    pub fn new(track: TrackId, key: KeyId) -> Self {
+        Self { track, key }
+    }
+}
+
+impl Timeline {
+    /// The selected keys in the order they were selected.
+    pub fn selection(&self) -> &[KeyRef] {
+        &self.selection
+    }
+
+    /// Replaces the selection, keys of unknown tracks are ignored.
+    pub fn set_selection(&mut self, keys: impl IntoIterator<Item = KeyRef>) {
+        self.selection.clear();
+        for key in keys {
+            if self.track_index(key.track).is_some() && !self.selection.contains(&key) {
+                self.selection.push(key);
+            }
+        }
+    }
 
-            ui.text("Footer")
+    pub fn select_key(&mut self, key: KeyRef) {
+        if !self.selection.contains(&key) {
+            self.selection.push(key);
         }
     }
+
+    pub fn deselect_key(&mut self, key: KeyRef) {
+        self.selection.retain(|k| *k != key);
+    }
+
+    pub fn is_key_selected(&self, key: KeyRef) -> bool {
+        self.selection.contains(&key)
+    }
+
+    pub fn clear_selection(&mut self) {
+        self.selection.clear();
+    }
+
+    /// Drops selected keys that no longer exist on their track.
+    fn prune_selection(&mut self) {
+        let tracks = &self.tracks;
+        self.selection.retain(|r| {
+            tracks
+                .iter()
+                .find(|e| e.id == r.track)
+                .is_some_and(|e| e.track.key_times().iter().any(|(k, _)| *k == r.key))
+        });
+    }
+}
+
+fn time_from_offset(left_time: Time, offset: f32, second_width: f32) -> Time {
+    left_time + Time::from_seconds((offset / second_width) as f64)
 }
 
 trait AsArray {
@@ -429,48 +530,192 @@ impl AsArray for Point {
     }
 }
 
-pub trait Track {
+pub trait AsAny: Any {
+    fn as_any(&self) -> &dyn Any;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+impl<T: Any> AsAny for T {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+pub trait Track: AsAny {
     fn draw_head(&mut self, ui: &imgui::Ui);
     fn head_config(&mut self) -> (String, f32);
-}
+    /// Draws the track body into its lane in the timeline column.
+    fn draw_lane(&mut self, _ui: &imgui::Ui, _view: &TrackView) {}
 
-pub struct KeyFrameTrack {
-    name: String,
+    /// The keys of the track that can be selected and dragged in the lane.
+    fn key_times(&self) -> Vec<(KeyId, Time)> {
+        Vec::new()
+    }
+    /// Creates a key at `time`, called when the lane is double-clicked.
+    fn add_key_at(&mut self, _time: Time) -> Option<KeyId> {
+        None
+    }
+    fn move_key(&mut self, _key: KeyId, _time: Time) -> bool {
+        false
+    }
+    fn delete_key(&mut self, _key: KeyId) -> bool {
+        false
+    }
+    /// End of a key that lasts, such keys are drawn as bars whose edges can be dragged.
+    fn key_end(&self, _key: KeyId) -> Option<Time> {
+        None
+    }
+    /// Trims a lasting key to `start..end`, `false` if the track rejects the range.
+    fn set_key_range(&mut self, _key: KeyId, _start: Time, _end: Time) -> bool {
+        false
+    }
+    /// Cuts a lasting key in two at `time`, returning the key of the second part.
+    fn split_key(&mut self, _key: KeyId, _time: Time) -> Option<KeyId> {
+        None
+    }
+    /// Everything needed to recreate the key, edits of keys without a state can't be undone.
+    fn key_state(&self, _key: KeyId) -> Option<KeyState> {
+        None
+    }
+    /// Recreates a key from [`Track::key_state`] with its id, replacing the key if it exists.
+    fn restore_key(&mut self, _state: &dyn Any) -> bool {
+        false
+    }
+
+    /// Instantaneous keys that [`Timeline::tick`] reports when playback crosses them.
+    fn trigger_times(&self) -> Vec<(KeyId, Time)> {
+        Vec::new()
+    }
+
+    /// Type of the keys in clipboard text, keys are only pasted onto tracks of the same type.
+    fn clipboard_type(&self) -> Option<&str> {
+        None
+    }
+    /// The key as a line of clipboard text, without its time.
+    fn copy_key(&self, _key: KeyId) -> Option<String> {
+        None
+    }
+    /// Creates a key at `time` from [`Track::copy_key`] text.
+    fn paste_key(&mut self, _time: Time, text: &str) -> Result<KeyId, PasteError> {
+        Err(PasteError::InvalidKey(text.to_string()))
+    }
+
+    /// The value of the track at `time`, used by [`Timeline::sample_all`].
+    fn sample(&self, _time: Time) -> Option<Value> {
+        None
+    }
+
+    /// Shown where the track head isn't drawn, like the graph editor's track list.
+    fn name(&self) -> Option<&str> {
+        None
+    }
+
+    /// Number of value curves the graph editor plots, e.g. 3 for a vector.
+    fn curve_count(&self) -> usize {
+        0
+    }
+    fn key_curve_value(&self, _key: KeyId, _curve: usize) -> Option<f32> {
+        None
+    }
+    /// Sets one curve of a key's value, called while dragging keys in the graph editor.
+    fn set_key_curve_value(&mut self, _key: KeyId, _curve: usize, _value: f32) -> bool {
+        false
+    }
+    /// The interpolated value of a curve, `None` where the track has no value.
+    fn sample_curve(&self, _time: Time, _curve: usize) -> Option<f32> {
+        None
+    }
+    /// Interpolation towards the next key, its bezier tangents are edited in the graph.
+    fn key_interpolation(&self, _key: KeyId) -> Option<Interpolation> {
+        None
+    }
+    fn set_key_interpolation(&mut self, _key: KeyId, _interpolation: Interpolation) -> bool {
+        false
+    }
+
+    /// Names the track type in saved documents, tracks without a tag aren't saved. Loading
+    /// needs a loader for the tag in the [`TrackRegistry`].
+    #[cfg(feature = "serde")]
+    fn type_tag(&self) -> Option<String> {
+        None
+    }
+    /// The content of the track for [`Timeline::save_document`].
+    #[cfg(feature = "serde")]
+    fn save(&self) -> Option<TrackData> {
+        None
+    }
 }
 
-impl KeyFrameTrack {
-    const TRACK_HEIGHT: f32 = 30.0;
+/// Identifies a key within its track.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct KeyId(pub u64);
+
+/// Everything a track needs to know to paint into its lane.
+pub struct TrackView<'a, 'ui> {
+    /// Draw list of the timeline window, clipped to the visible part of the lane.
+    pub draw_list: &'a imgui::DrawListMut<'ui>,
+    pub lane_min: Point,
+    pub lane_max: Point,
+    /// Time at the left edge of the lane.
+    pub left_time: Time,
+    /// Time at the right edge of the lane.
+    pub right_time: Time,
+    pub pixels_per_second: f32,
+    pub playhead: Time,
+    pub selected_keys: &'a [KeyId],
 }
 
-impl Track for KeyFrameTrack {
-    fn draw_head(&mut self, ui: &imgui::Ui) {
-        let max: Point = ui.content_region_max().into();
-        let size: Point = ui.calc_text_size(&self.name).into();
-        ui.set_cursor_pos([
-            (max.x - size.x) / 2.0,
-            ui.cursor_pos()[1] + (Self::TRACK_HEIGHT - ui.text_line_height_with_spacing()) / 2.0,
-        ]);
-        ui.text(&self.name);
+impl TrackView<'_, '_> {
+    pub fn time_to_x(&self, time: Time) -> f32 {
+        self.lane_min.x + (time - self.left_time).as_seconds() as f32 * self.pixels_per_second
+    }
+
+    pub fn x_to_time(&self, x: f32) -> Time {
+        time_from_offset(self.left_time, x - self.lane_min.x, self.pixels_per_second)
+    }
+
+    pub fn is_selected(&self, key: KeyId) -> bool {
+        self.selected_keys.contains(&key)
     }
 
-    fn head_config(&mut self) -> (String, f32) {
-        (
-            format!("keyframe_track_head_{}", self.name),
-            Self::TRACK_HEIGHT,
-        )
+    pub fn lane_size(&self) -> Point {
+        self.lane_max - self.lane_min
     }
 }
 
-fn draw_track(track: &mut Box<dyn Track>, ui: &imgui::Ui) {
-    let config = track.head_config();
-    if let Some(_c) = ui
-        .child_window(config.0)
-        .scroll_bar(false)
-        .scrollable(false)
-        .size([0.0, config.1])
-        .begin()
-    {
-        track.draw_head(ui);
-    }
-    ui.next_column();
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn order(timeline: &Timeline) -> Vec<TrackId> {
+        timeline.tracks().map(|(id, _)| id).collect()
+    }
+
+    #[test]
+    fn inserts_removes_and_moves_tracks() {
+        let mut timeline = Timeline::new("test");
+        let a = timeline.add_track(EventTrack::new("a"));
+        let b = timeline.add_track(EventTrack::new("b"));
+        let c = timeline.insert_track(1, EventTrack::new("c"));
+        let d = timeline.insert_track(99, EventTrack::new("d"));
+        assert_eq!(order(&timeline), [a, c, b, d]);
+        assert_eq!(timeline.track(c).and_then(|t| t.name()), Some("c"));
+
+        assert!(timeline.move_track(a, 99));
+        assert!(timeline.move_track(d, 0));
+        assert_eq!(order(&timeline), [d, c, b, a]);
+
+        let key = timeline.add_key(c, Time::ZERO).unwrap();
+        timeline.set_selection([key]);
+        assert!(timeline.remove_track(c));
+        assert!(!timeline.remove_track(c));
+        assert!(!timeline.move_track(c, 0));
+        assert!(timeline.selection().is_empty());
+        assert_eq!(order(&timeline), [d, b, a]);
+        assert_eq!(timeline.track_index(a), Some(2));
+    }
 }
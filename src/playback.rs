@@ -0,0 +1,607 @@
+use std::cmp::Reverse;
+
+use crate::{KeyRef, Time, Timeline};
+
+/// An event on a track that playback crossed, see [`Timeline::tick`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CrossedEvent {
+    pub key: KeyRef,
+    pub time: Time,
+}
+
+/// What happens when playback reaches the end of the playback range.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum PlaybackMode {
+    /// Jumps back to the start of the range.
+    #[default]
+    Loop,
+    /// Reverses direction at either end of the range.
+    PingPong,
+    /// Stops at the end of the range.
+    Once,
+}
+
+impl PlaybackMode {
+    pub const ALL: [Self; 3] = [Self::Loop, Self::PingPong, Self::Once];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Loop => "Loop",
+            Self::PingPong => "Ping-pong",
+            Self::Once => "Once",
+        }
+    }
+}
+
+/// Speeds the J/L shuttle keys step through, negated for J.
+const SHUTTLE_SPEEDS: [f32; 4] = [1.0, 2.0, 4.0, 8.0];
+
+/// A stretch the playhead moved over during a tick, in either direction. Events at `from`
+/// only count if the playhead did not already stop on them.
+struct Pass {
+    from: Time,
+    to: Time,
+    inclusive: bool,
+}
+
+impl Pass {
+    fn contains(&self, time: Time) -> bool {
+        if time == self.from {
+            return self.inclusive;
+        }
+        self.from.min(self.to) <= time && time <= self.from.max(self.to)
+    }
+}
+
+impl Timeline {
+    /// Advances playback by `dt` seconds, independent of whether the timeline is drawn.
+    /// Returns the events of [`crate::Track::trigger_times`] the playhead crossed, in the order it
+    /// crossed them, including the ones passed before wrapping or bouncing at the ends of the
+    /// playback range. Events at the playhead count once playback arrives there, or on the
+    /// first tick after the playhead was moved some other way.
+    pub fn tick(&mut self, dt: f32) -> Vec<CrossedEvent> {
+        if !self.playing || self.scrubbing {
+            return Vec::new();
+        }
+        let (start, end) = self.playback_range();
+        let before = self.time;
+        let mut from = before;
+        let mut inclusive = self.last_tick_time != Some(before);
+        let mut remaining = Time::from_seconds(dt as f64 * self.playback_speed as f64).ticks();
+        let length = (end - start).ticks();
+        let mut passes = Vec::new();
+
+        // A playhead outside the range, e.g. moved there while playing, plays on freely.
+        let after = before + Time::from_ticks(remaining);
+        if before < start || before > end || (start <= after && after <= end) {
+            passes.push(Pass {
+                from,
+                to: after,
+                inclusive,
+            });
+        } else if length <= 0 {
+            if self.playback_mode == PlaybackMode::Once {
+                self.playing = false;
+            }
+        } else {
+            // Whole cycles end where they started, without events in the range they can be
+            // skipped.
+            if !self.has_triggers_between(start, end) {
+                let period = match self.playback_mode {
+                    PlaybackMode::PingPong => length * 2,
+                    _ => length,
+                };
+                let skipped = (remaining.abs() / period - 1).max(0);
+                remaining -= remaining.signum() * skipped * period;
+            }
+
+            loop {
+                let bound = if remaining < 0 { start } else { end };
+                let reach = (bound - from).ticks();
+                if remaining.abs() <= reach.abs() {
+                    passes.push(Pass {
+                        from,
+                        to: from + Time::from_ticks(remaining),
+                        inclusive,
+                    });
+                    break;
+                }
+                passes.push(Pass {
+                    from,
+                    to: bound,
+                    inclusive,
+                });
+                remaining -= reach;
+                match self.playback_mode {
+                    PlaybackMode::Loop => {
+                        from = if remaining < 0 { end } else { start };
+                        inclusive = true;
+                    }
+                    PlaybackMode::PingPong => {
+                        from = bound;
+                        remaining = -remaining;
+                        inclusive = false;
+                        self.playback_speed = -self.playback_speed;
+                    }
+                    PlaybackMode::Once => {
+                        self.playing = false;
+                        break;
+                    }
+                }
+            }
+        }
+
+        self.time = passes.last().map_or(start, |pass| pass.to);
+        self.last_tick_time = Some(self.time);
+        self.crossed_events(&passes)
+    }
+
+    fn has_triggers_between(&self, start: Time, end: Time) -> bool {
+        self.tracks.iter().any(|entry| {
+            entry
+                .track
+                .trigger_times()
+                .iter()
+                .any(|&(_, time)| start <= time && time <= end)
+        })
+    }
+
+    fn crossed_events(&self, passes: &[Pass]) -> Vec<CrossedEvent> {
+        let triggers: Vec<CrossedEvent> = self
+            .tracks
+            .iter()
+            .flat_map(|entry| {
+                entry
+                    .track
+                    .trigger_times()
+                    .into_iter()
+                    .map(move |(key, time)| CrossedEvent {
+                        key: KeyRef::new(entry.id, key),
+                        time,
+                    })
+            })
+            .collect();
+        let mut crossed = Vec::new();
+        if triggers.is_empty() {
+            return crossed;
+        }
+        for pass in passes {
+            let first = crossed.len();
+            crossed.extend(triggers.iter().filter(|e| pass.contains(e.time)));
+            if pass.to < pass.from {
+                crossed[first..].sort_by_key(|e| Reverse(e.time));
+            } else {
+                crossed[first..].sort_by_key(|e| e.time);
+            }
+        }
+        crossed
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.playing
+    }
+
+    /// Starting playback with the playhead outside the playback range, or at the end it plays
+    /// towards, jumps to the other end of the range.
+    pub fn set_playing(&mut self, playing: bool) {
+        if playing && !self.playing {
+            let (start, end) = self.playback_range();
+            if self.playback_speed < 0.0 {
+                if self.time <= start || self.time > end {
+                    self.time = end;
+                }
+            } else if self.playback_speed > 0.0 && (self.time < start || self.time >= end) {
+                self.time = start;
+            }
+        }
+        self.playing = playing;
+    }
+
+    /// Whether playback runs towards earlier times, i.e. the playback speed is negative.
+    pub fn is_reversed(&self) -> bool {
+        self.playback_speed < 0.0
+    }
+
+    /// Plays forward from the playhead, keeping the magnitude of the playback speed.
+    pub fn play(&mut self) {
+        if self.playback_speed < 0.0 {
+            self.playing = false;
+            self.playback_speed = -self.playback_speed;
+        }
+        self.set_playing(true);
+    }
+
+    /// Plays backward from the playhead, keeping the magnitude of the playback speed.
+    pub fn play_reverse(&mut self) {
+        if self.playback_speed > 0.0 {
+            self.playing = false;
+            self.playback_speed = -self.playback_speed;
+        }
+        self.set_playing(true);
+    }
+
+    /// Shuttles forward like the L key, every press doubles the speed up to 8x.
+    pub fn shuttle_forward(&mut self) {
+        self.shuttle(1.0);
+    }
+
+    /// Shuttles backward like the J key, every press doubles the reverse speed up to 8x.
+    pub fn shuttle_backward(&mut self) {
+        self.shuttle(-1.0);
+    }
+
+    fn shuttle(&mut self, direction: f32) {
+        let current = self.playback_speed * direction;
+        let speed = if self.playing && current > 0.0 {
+            SHUTTLE_SPEEDS
+                .into_iter()
+                .find(|speed| *speed > current)
+                .unwrap_or(SHUTTLE_SPEEDS[SHUTTLE_SPEEDS.len() - 1])
+        } else {
+            SHUTTLE_SPEEDS[0]
+        };
+        self.playing = false;
+        self.playback_speed = speed * direction;
+        self.set_playing(true);
+    }
+
+    pub fn pause(&mut self) {
+        self.playing = false;
+    }
+
+    pub fn toggle_playing(&mut self) {
+        self.set_playing(!self.playing);
+    }
+
+    /// Pauses and returns to the start of the playback range.
+    pub fn stop(&mut self) {
+        self.playing = false;
+        self.playback_speed = self.playback_speed.abs();
+        self.time = self.playback_range().0;
+    }
+
+    /// Pauses and moves the playhead by `frames`, negative steps backward.
+    pub fn step_frames(&mut self, frames: i64) {
+        self.playing = false;
+        self.time = Time::from_frames(
+            self.display_time().frame(self.frame_rate) + frames,
+            self.frame_rate,
+        );
+    }
+
+    /// Moves the playhead to the closest key before it on any track, `false` if there is none.
+    pub fn jump_to_previous_key(&mut self) -> bool {
+        let current = self.display_time();
+        let previous = self
+            .tracks
+            .iter()
+            .flat_map(|e| e.track.key_times())
+            .map(|(_, time)| time)
+            .filter(|time| *time < current)
+            .max();
+        if let Some(time) = previous {
+            self.time = time;
+        }
+        previous.is_some()
+    }
+
+    /// Moves the playhead to the closest key after it on any track, `false` if there is none.
+    pub fn jump_to_next_key(&mut self) -> bool {
+        let current = self.display_time();
+        let next = self
+            .tracks
+            .iter()
+            .flat_map(|e| e.track.key_times())
+            .map(|(_, time)| time)
+            .filter(|time| *time > current)
+            .min();
+        if let Some(time) = next {
+            self.time = time;
+        }
+        next.is_some()
+    }
+
+    pub fn jump_to_range_start(&mut self) {
+        self.time = self.playback_range().0;
+    }
+
+    pub fn jump_to_range_end(&mut self) {
+        self.time = self.playback_range().1;
+    }
+
+    pub fn playback_speed(&self) -> f32 {
+        self.playback_speed
+    }
+
+    /// Negative speeds play backward, at zero the playhead only moves by scrubbing.
+    pub fn set_playback_speed(&mut self, speed: f32) {
+        self.playback_speed = speed;
+    }
+
+    pub fn playback_mode(&self) -> PlaybackMode {
+        self.playback_mode
+    }
+
+    pub fn set_playback_mode(&mut self, mode: PlaybackMode) {
+        self.playback_mode = mode;
+    }
+
+    /// The range playback stays in, the in/out points or the whole duration without them.
+    pub fn playback_range(&self) -> (Time, Time) {
+        (
+            self.in_point.unwrap_or(Time::ZERO),
+            self.out_point.unwrap_or(self.duration),
+        )
+    }
+
+    pub fn in_point(&self) -> Option<Time> {
+        self.in_point
+    }
+
+    /// Sets the in point, one after the end of the playback range swaps with the end.
+    pub fn set_in_point(&mut self, time: Option<Time>) {
+        let before = (self.in_point, self.out_point);
+        self.in_point = time;
+        self.order_playback_range();
+        self.record_playback_range(before);
+    }
+
+    pub fn out_point(&self) -> Option<Time> {
+        self.out_point
+    }
+
+    /// Sets the out point, one before the start of the playback range swaps with the start.
+    pub fn set_out_point(&mut self, time: Option<Time>) {
+        let before = (self.in_point, self.out_point);
+        self.out_point = time;
+        self.order_playback_range();
+        self.record_playback_range(before);
+    }
+
+    fn order_playback_range(&mut self) {
+        let (start, end) = self.playback_range();
+        if start > end {
+            self.in_point = Some(end);
+            self.out_point = Some(start);
+        }
+    }
+
+    /// Sets both in and out point, in either order.
+    pub fn set_playback_range(&mut self, start: Time, end: Time) {
+        let before = (self.in_point, self.out_point);
+        self.in_point = Some(start.min(end));
+        self.out_point = Some(start.max(end));
+        self.record_playback_range(before);
+    }
+
+    pub fn clear_playback_range(&mut self) {
+        let before = (self.in_point, self.out_point);
+        self.in_point = None;
+        self.out_point = None;
+        self.record_playback_range(before);
+    }
+
+    /// Whether an in or out point is set.
+    pub fn has_playback_range(&self) -> bool {
+        self.in_point.is_some() || self.out_point.is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{EventTrack, TrackId};
+
+    fn ms(millis: i64) -> Time {
+        Time::from_millis(millis)
+    }
+
+    /// A 10 s timeline with events at 0, 1, 5, 9 and 10 s, named by their time in ms.
+    fn timeline(mode: PlaybackMode) -> (Timeline, TrackId) {
+        let mut timeline = Timeline::new("test");
+        timeline.set_duration(ms(10_000));
+        timeline.set_playback_mode(mode);
+        let mut events = EventTrack::new("events");
+        for millis in [0, 1000, 5000, 9000, 10_000] {
+            events.insert_event(ms(millis), millis.to_string());
+        }
+        let track = timeline.add_track(events);
+        (timeline, track)
+    }
+
+    fn tick(timeline: &mut Timeline, track: TrackId, dt: f32) -> Vec<String> {
+        let crossed = timeline.tick(dt);
+        let events = timeline.track_as::<EventTrack>(track).unwrap();
+        crossed
+            .iter()
+            .map(|c| events.event(c.key.key).unwrap().payload().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn reports_events_once() {
+        let (mut timeline, track) = timeline(PlaybackMode::Loop);
+        timeline.set_playing(true);
+        assert_eq!(tick(&mut timeline, track, 0.5), ["0"]);
+        assert_eq!(tick(&mut timeline, track, 0.5), ["1000"]);
+        assert!(tick(&mut timeline, track, 0.0).is_empty());
+        assert!(tick(&mut timeline, track, 0.5).is_empty());
+        assert_eq!(timeline.time(), ms(1500));
+    }
+
+    #[test]
+    fn reports_events_at_a_moved_playhead() {
+        let (mut timeline, track) = timeline(PlaybackMode::Loop);
+        timeline.set_playing(true);
+        tick(&mut timeline, track, 0.5);
+        timeline.set_time(ms(5000));
+        assert_eq!(tick(&mut timeline, track, 0.5), ["5000"]);
+    }
+
+    #[test]
+    fn reports_events_across_a_loop() {
+        let (mut timeline, track) = timeline(PlaybackMode::Loop);
+        timeline.set_time(ms(1000));
+        timeline.set_playing(true);
+        tick(&mut timeline, track, 0.0);
+        assert_eq!(
+            tick(&mut timeline, track, 9.5),
+            ["5000", "9000", "10000", "0"]
+        );
+        assert_eq!(timeline.time(), ms(500));
+    }
+
+    #[test]
+    fn reports_events_in_reverse() {
+        let (mut timeline, track) = timeline(PlaybackMode::Loop);
+        timeline.set_time(ms(1500));
+        timeline.set_playback_speed(-1.0);
+        timeline.set_playing(true);
+        assert_eq!(
+            tick(&mut timeline, track, 2.5),
+            ["1000", "0", "10000", "9000"]
+        );
+        assert_eq!(timeline.time(), ms(9000));
+    }
+
+    #[test]
+    fn reports_events_across_ping_pong_bounces() {
+        let (mut timeline, track) = timeline(PlaybackMode::PingPong);
+        timeline.set_time(ms(8000));
+        timeline.set_playing(true);
+        assert_eq!(tick(&mut timeline, track, 4.0), ["9000", "10000", "9000"]);
+        assert_eq!(timeline.time(), ms(8000));
+        assert!(timeline.is_reversed());
+        assert_eq!(
+            tick(&mut timeline, track, 9.0),
+            ["5000", "1000", "0", "1000"]
+        );
+        assert_eq!(timeline.time(), ms(1000));
+        assert!(!timeline.is_reversed());
+    }
+
+    #[test]
+    fn stops_after_the_last_event_once() {
+        let (mut timeline, track) = timeline(PlaybackMode::Once);
+        timeline.set_time(ms(4000));
+        timeline.set_playing(true);
+        assert_eq!(tick(&mut timeline, track, 100.0), ["5000", "9000", "10000"]);
+        assert_eq!(timeline.time(), ms(10_000));
+        assert!(!timeline.is_playing());
+    }
+
+    #[test]
+    fn reports_every_loop_of_a_large_step() {
+        let (mut timeline, track) = timeline(PlaybackMode::Loop);
+        timeline.set_time(ms(500));
+        timeline.set_playing(true);
+        tick(&mut timeline, track, 0.0);
+        // To the end, a whole loop and on to 5.5 s.
+        assert_eq!(
+            tick(&mut timeline, track, 25.0),
+            [
+                "1000", "5000", "9000", "10000", "0", "1000", "5000", "9000", "10000", "0", "1000",
+                "5000"
+            ]
+        );
+        assert_eq!(timeline.time(), ms(5500));
+
+        let mut expected = vec!["9000", "10000"];
+        for _ in 0..999 {
+            expected.extend(["0", "1000", "5000", "9000", "10000"]);
+        }
+        expected.extend(["0", "1000", "5000"]);
+        assert_eq!(tick(&mut timeline, track, 10_000.0), expected);
+        assert_eq!(timeline.time(), ms(5500));
+    }
+
+    #[test]
+    fn skips_whole_cycles_without_events() {
+        let mut timeline = Timeline::new("test");
+        timeline.set_duration(ms(10_000));
+        timeline.set_playback_mode(PlaybackMode::PingPong);
+        timeline.add_track(EventTrack::new("events"));
+        timeline.set_playing(true);
+        timeline.set_time(ms(2000));
+        // 100000 round trips, 8 s to the end and 5 s back.
+        assert!(timeline.tick(2_000_013.0).is_empty());
+        assert_eq!(timeline.time(), ms(5000));
+        assert!(timeline.is_reversed());
+    }
+
+    #[test]
+    fn plays_freely_outside_the_range() {
+        let (mut timeline, track) = timeline(PlaybackMode::Loop);
+        timeline.set_out_point(Some(ms(2000)));
+        // Starting playback outside the range would jump into it.
+        timeline.set_playing(true);
+        timeline.set_time(ms(4000));
+        tick(&mut timeline, track, 0.0);
+        assert_eq!(tick(&mut timeline, track, 1.5), ["5000"]);
+        assert_eq!(timeline.time(), ms(5500));
+    }
+
+    #[test]
+    fn keeps_in_and_out_point_in_order() {
+        let mut timeline = Timeline::new("test");
+        timeline.set_duration(ms(10_000));
+        timeline.set_out_point(Some(ms(4000)));
+        timeline.set_in_point(Some(ms(6000)));
+        assert_eq!(timeline.playback_range(), (ms(4000), ms(6000)));
+        timeline.set_out_point(Some(ms(1000)));
+        assert_eq!(timeline.playback_range(), (ms(1000), ms(4000)));
+
+        timeline.clear_playback_range();
+        timeline.set_in_point(Some(ms(12_000)));
+        assert_eq!(timeline.in_point(), Some(ms(10_000)));
+        assert_eq!(timeline.out_point(), Some(ms(12_000)));
+    }
+
+    #[test]
+    fn steps_to_keys_and_frames() {
+        let (mut timeline, _) = timeline(PlaybackMode::Loop);
+        timeline.set_time(ms(3000));
+        assert!(timeline.jump_to_previous_key());
+        assert_eq!(timeline.time(), ms(1000));
+        assert!(timeline.jump_to_next_key());
+        assert_eq!(timeline.time(), ms(5000));
+        timeline.set_time(ms(10_000));
+        assert!(!timeline.jump_to_next_key());
+        assert_eq!(timeline.time(), ms(10_000));
+        timeline.set_time(Time::ZERO);
+        assert!(!timeline.jump_to_previous_key());
+
+        timeline.set_frame_rate(crate::FrameRate::FPS_25);
+        timeline.set_time(ms(1000));
+        timeline.set_playing(true);
+        timeline.step_frames(1);
+        assert!(!timeline.is_playing());
+        assert_eq!(timeline.time(), ms(1040));
+        timeline.step_frames(-2);
+        assert_eq!(timeline.time(), ms(960));
+    }
+
+    #[test]
+    fn shuttle_steps_through_speeds() {
+        let (mut timeline, _) = timeline(PlaybackMode::Loop);
+        timeline.set_time(ms(5000));
+        let mut speeds = Vec::new();
+        for _ in 0..5 {
+            timeline.shuttle_forward();
+            speeds.push(timeline.playback_speed());
+        }
+        assert_eq!(speeds, [1.0, 2.0, 4.0, 8.0, 8.0]);
+
+        // Reversing starts over at normal speed, as does pausing.
+        timeline.shuttle_backward();
+        assert_eq!(timeline.playback_speed(), -1.0);
+        timeline.shuttle_backward();
+        assert_eq!(timeline.playback_speed(), -2.0);
+        assert!(timeline.is_playing() && timeline.is_reversed());
+        timeline.pause();
+        timeline.shuttle_backward();
+        assert_eq!(timeline.playback_speed(), -1.0);
+        assert_eq!(timeline.time(), ms(5000));
+    }
+}
@@ -0,0 +1,133 @@
+use std::collections::HashMap;
+
+use serde::de::DeserializeOwned;
+
+use crate::{ClipTrack, DocumentError, EventTrack, KeyFrameTrack, KeyValue, Track, TrackData};
+
+type TrackLoader = Box<dyn Fn(TrackData) -> Option<Box<dyn Track>>>;
+
+/// Creates tracks of the right concrete type from saved documents, keyed by
+/// [`Track::type_tag`]. The default registry knows the built-in track types, host
+/// applications add their own before calling [`crate::Timeline::load_document_with`].
+pub struct TrackRegistry {
+    loaders: HashMap<String, TrackLoader>,
+}
+
+impl TrackRegistry {
+    /// A registry without any track types, not even the built-in ones.
+    pub fn empty() -> Self {
+        Self {
+            loaders: HashMap::new(),
+        }
+    }
+
+    /// Registers `load` for tracks saved with the `kind` tag, replacing an earlier loader.
+    pub fn register<T: Track>(
+        &mut self,
+        kind: impl Into<String>,
+        load: impl Fn(TrackData) -> Option<T> + 'static,
+    ) {
+        self.loaders.insert(
+            kind.into(),
+            Box::new(move |data| Some(Box::new(load(data)?) as Box<dyn Track>)),
+        );
+    }
+
+    /// Registers a track type that saves itself with [`crate::to_track_data`].
+    pub fn register_serde<T: Track + DeserializeOwned>(&mut self, kind: impl Into<String>) {
+        self.register(kind, |data| data.deserialize_into::<T>().ok());
+    }
+
+    /// Registers keyframe tracks of a custom [`KeyValue`] that implements
+    /// [`KeyValue::to_data`] and [`KeyValue::from_data`], tagged by [`KeyValue::type_name`].
+    pub fn register_keyframes<V: KeyValue>(&mut self) {
+        self.register(KeyFrameTrack::<V>::type_tag(), KeyFrameTrack::<V>::load);
+    }
+
+    pub fn is_registered(&self, kind: &str) -> bool {
+        self.loaders.contains_key(kind)
+    }
+
+    /// Creates a track from its saved data.
+    pub fn load(&self, kind: &str, data: TrackData) -> Result<Box<dyn Track>, DocumentError> {
+        let load = self
+            .loaders
+            .get(kind)
+            .ok_or_else(|| DocumentError::UnknownTrackType(kind.to_string()))?;
+        load(data).ok_or_else(|| DocumentError::InvalidTrack(kind.to_string()))
+    }
+}
+
+impl Default for TrackRegistry {
+    fn default() -> Self {
+        let mut registry = Self::empty();
+        registry.register_keyframes::<f32>();
+        registry.register_keyframes::<f64>();
+        registry.register_keyframes::<bool>();
+        registry.register_keyframes::<[f32; 3]>();
+        registry.register_keyframes::<[f32; 4]>();
+        registry.register_keyframes::<cgmath::Vector2<f32>>();
+        registry.register_keyframes::<cgmath::Vector3<f32>>();
+        registry.register_keyframes::<cgmath::Vector4<f32>>();
+        registry.register(ClipTrack::TYPE_TAG, ClipTrack::load);
+        registry.register(EventTrack::TYPE_TAG, EventTrack::load);
+        registry
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::{Deserialize, Serialize};
+
+    use super::*;
+    use crate::{to_track_data, Time, Timeline, TimelineDocument};
+
+    #[derive(Serialize, Deserialize)]
+    struct CameraTrack {
+        name: String,
+        cuts: Vec<Time>,
+    }
+
+    impl Track for CameraTrack {
+        fn draw_head(&mut self, _ui: &imgui::Ui) {}
+
+        fn head_config(&mut self) -> (String, f32) {
+            (self.name.clone(), 24.0)
+        }
+
+        fn type_tag(&self) -> Option<String> {
+            Some("camera".to_string())
+        }
+
+        fn save(&self) -> Option<TrackData> {
+            to_track_data(self)
+        }
+    }
+
+    #[test]
+    fn custom_tracks_round_trip() {
+        let mut timeline = Timeline::new("test");
+        timeline.add_track(CameraTrack {
+            name: "Camera".to_string(),
+            cuts: vec![Time::from_millis(1500)],
+        });
+        let document = timeline.save_document();
+        let document: TimelineDocument = serde_value::to_value(&document)
+            .unwrap()
+            .deserialize_into()
+            .unwrap();
+
+        let mut loaded = Timeline::new("loaded");
+        assert_eq!(
+            loaded.load_document(document.clone()).err(),
+            Some(DocumentError::UnknownTrackType("camera".to_string()))
+        );
+        let mut registry = TrackRegistry::default();
+        registry.register_serde::<CameraTrack>("camera");
+        loaded.load_document_with(document, &registry).unwrap();
+        let id = loaded.tracks[0].id;
+        let camera = loaded.track_as::<CameraTrack>(id).unwrap();
+        assert_eq!(camera.name, "Camera");
+        assert_eq!(camera.cuts, [Time::from_millis(1500)]);
+    }
+}
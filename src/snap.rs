@@ -0,0 +1,161 @@
+use crate::{KeyRef, Time, Timeline};
+
+/// What dragged keys, clips and the playhead snap to. Holding Shift while dragging
+/// disables snapping.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct SnapSettings {
+    /// Rounds to whole frames when no other target is in reach.
+    pub frames: bool,
+    pub ruler_ticks: bool,
+    pub keys: bool,
+    pub markers: bool,
+    pub playhead: bool,
+    /// How close, in pixels, a time has to come to a target to snap to it.
+    pub distance: f32,
+}
+
+impl Default for SnapSettings {
+    fn default() -> Self {
+        Self {
+            frames: true,
+            ruler_ticks: false,
+            keys: true,
+            markers: true,
+            playhead: true,
+            distance: 8.0,
+        }
+    }
+}
+
+/// A named point in time, drawn on the ruler.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Marker {
+    pub time: Time,
+    pub name: String,
+}
+
+impl Marker {
+    pub fn new(time: Time, name: impl Into<String>) -> Self {
+        Self {
+            time,
+            name: name.into(),
+        }
+    }
+}
+
+impl Timeline {
+    pub fn snap_settings(&self) -> SnapSettings {
+        self.snap
+    }
+
+    pub fn set_snap_settings(&mut self, settings: SnapSettings) {
+        self.snap = settings;
+    }
+
+    pub fn markers(&self) -> &[Marker] {
+        &self.markers
+    }
+
+    pub fn markers_mut(&mut self) -> &mut Vec<Marker> {
+        &mut self.markers
+    }
+
+    pub fn add_marker(&mut self, marker: Marker) {
+        self.markers.push(marker);
+    }
+
+    /// The target in snapping distance closest to any of `times`, as the offset that moves
+    /// that time onto the target and the target itself. Keys in `ignore` are not targets,
+    /// usually the dragged ones.
+    pub(crate) fn find_snap(
+        &self,
+        times: &[Time],
+        ignore: &[KeyRef],
+        snap_to_playhead: bool,
+        second_width: f32,
+    ) -> Option<(Time, Time)> {
+        let mut targets = Vec::new();
+        if self.snap.keys {
+            for entry in &self.tracks {
+                for (key, time) in entry.track.key_times() {
+                    if !ignore.contains(&KeyRef::new(entry.id, key)) {
+                        targets.push(time);
+                        targets.extend(entry.track.key_end(key));
+                    }
+                }
+            }
+        }
+        if self.snap.markers {
+            targets.extend(self.markers.iter().map(|m| m.time));
+        }
+        if self.snap.playhead && snap_to_playhead {
+            targets.push(self.display_time());
+        }
+
+        let reach = Time::from_seconds((self.snap.distance / second_width) as f64);
+        let mut best: Option<(Time, Time)> = None;
+        for &time in times {
+            let tick = self.snap.ruler_ticks.then(|| self.nearest_ruler_tick(time));
+            for target in targets.iter().copied().chain(tick) {
+                let offset = target - time;
+                if offset.abs() <= reach && best.is_none_or(|(b, _)| offset.abs() < b.abs()) {
+                    best = Some((offset, target));
+                }
+            }
+        }
+        best
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{EventTrack, FrameRate};
+
+    fn ms(millis: i64) -> Time {
+        Time::from_millis(millis)
+    }
+
+    #[test]
+    fn snaps_to_the_closest_target_in_reach() {
+        let mut timeline = Timeline::new("test");
+        timeline.set_frame_rate(FrameRate::FPS_25);
+        let mut events = EventTrack::new("events");
+        let dragged = events.insert_event(ms(1000), "");
+        events.insert_event(ms(2000), "");
+        let track = timeline.add_track(events);
+        let ignore = [KeyRef::new(track, dragged)];
+        timeline.add_marker(Marker::new(ms(3000), "M1"));
+        timeline.set_time(ms(400));
+
+        // 8 pixels at 100 pixels per second reach 80 ms.
+        let snap = |timeline: &Timeline, times: &[i64], playhead: bool, width: f32| {
+            let times = times.iter().copied().map(ms).collect::<Vec<_>>();
+            timeline.find_snap(&times, &ignore, playhead, width)
+        };
+        assert_eq!(
+            snap(&timeline, &[1950], true, 100.0),
+            Some((ms(50), ms(2000)))
+        );
+        assert_eq!(snap(&timeline, &[1000], true, 100.0), None);
+        assert_eq!(
+            snap(&timeline, &[1950, 2990], true, 100.0),
+            Some((ms(10), ms(3000)))
+        );
+        assert_eq!(
+            snap(&timeline, &[430], true, 100.0),
+            Some((ms(-30), ms(400)))
+        );
+        assert_eq!(snap(&timeline, &[430], false, 100.0), None);
+        assert_eq!(snap(&timeline, &[1950], true, 1000.0), None);
+
+        timeline.set_snap_settings(SnapSettings {
+            markers: false,
+            ..SnapSettings::default()
+        });
+        assert_eq!(snap(&timeline, &[2990], true, 100.0), None);
+    }
+}
@@ -0,0 +1,658 @@
+use std::{
+    error::Error,
+    fmt,
+    ops::{Add, AddAssign, Div, Mul, Neg, Sub, SubAssign},
+};
+
+/// A point in, or span of, timeline time.
+///
+/// Stored as an integer count of ticks at [`Time::TICKS_PER_SECOND`], a rate that
+/// divides evenly by all common frame rates (including 30000/1001) and by milliseconds,
+/// so frame and millisecond arithmetic is exact and playback never accumulates rounding.
+/// Can be negative.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
+pub struct Time(i64);
+
+impl Time {
+    pub const TICKS_PER_SECOND: i64 = 705_600_000;
+    pub const ZERO: Self = Self(0);
+    pub const MIN: Self = Self(i64::MIN);
+    pub const MAX: Self = Self(i64::MAX);
+
+    pub const fn from_ticks(ticks: i64) -> Self {
+        Self(ticks)
+    }
+
+    pub const fn ticks(self) -> i64 {
+        self.0
+    }
+
+    /// Rounds to the nearest tick.
+    pub fn from_seconds(seconds: f64) -> Self {
+        Self((seconds * Self::TICKS_PER_SECOND as f64).round() as i64)
+    }
+
+    pub fn as_seconds(self) -> f64 {
+        self.0 as f64 / Self::TICKS_PER_SECOND as f64
+    }
+
+    pub const fn from_millis(millis: i64) -> Self {
+        Self(millis * (Self::TICKS_PER_SECOND / 1000))
+    }
+
+    /// Whole milliseconds, rounded towards negative infinity.
+    pub const fn as_millis(self) -> i64 {
+        self.0.div_euclid(Self::TICKS_PER_SECOND / 1000)
+    }
+
+    /// Start of frame `frame` at `rate`, saturating at [`Time::MIN`] and [`Time::MAX`].
+    pub fn from_frames(frame: i64, rate: FrameRate) -> Self {
+        Self::checked_from_frames(frame, rate).unwrap_or(if frame < 0 {
+            Self::MIN
+        } else {
+            Self::MAX
+        })
+    }
+
+    /// Start of frame `frame` at `rate`, `None` if it is out of range.
+    pub fn checked_from_frames(frame: i64, rate: FrameRate) -> Option<Self> {
+        let ticks = frame as i128 * Self::TICKS_PER_SECOND as i128 * rate.denominator as i128
+            / rate.numerator as i128;
+        i64::try_from(ticks).ok().map(Self)
+    }
+
+    /// Time in frames at `rate`, including the fraction into the current frame.
+    pub fn as_frames(self, rate: FrameRate) -> f64 {
+        self.as_seconds() * rate.as_fps()
+    }
+
+    /// The frame this time falls into at `rate`.
+    pub fn frame(self, rate: FrameRate) -> i64 {
+        let ticks_per_frame = Self::TICKS_PER_SECOND as i128 * rate.denominator as i128;
+        (self.0 as i128 * rate.numerator as i128).div_euclid(ticks_per_frame) as i64
+    }
+
+    /// Start of the frame this time falls into.
+    pub fn floor_to_frame(self, rate: FrameRate) -> Self {
+        Self::from_frames(self.frame(rate), rate)
+    }
+
+    /// The closest frame boundary.
+    pub fn round_to_frame(self, rate: FrameRate) -> Self {
+        let ticks_per_frame = Self::TICKS_PER_SECOND as i128 * rate.denominator as i128;
+        let frame = (self.0 as i128 * rate.numerator as i128 * 2 + ticks_per_frame)
+            .div_euclid(ticks_per_frame * 2);
+        Self::from_frames(frame as i64, rate)
+    }
+
+    pub fn abs(self) -> Self {
+        Self(self.0.saturating_abs())
+    }
+
+    pub fn min(self, other: Self) -> Self {
+        Ord::min(self, other)
+    }
+
+    pub fn max(self, other: Self) -> Self {
+        Ord::max(self, other)
+    }
+
+    pub fn clamp(self, min: Self, max: Self) -> Self {
+        Ord::clamp(self, min, max)
+    }
+
+    pub fn is_negative(self) -> bool {
+        self.0 < 0
+    }
+}
+
+impl Add for Time {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self {
+        Self(self.0.saturating_add(rhs.0))
+    }
+}
+
+impl AddAssign for Time {
+    fn add_assign(&mut self, rhs: Self) {
+        *self = *self + rhs;
+    }
+}
+
+impl Sub for Time {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self {
+        Self(self.0.saturating_sub(rhs.0))
+    }
+}
+
+impl SubAssign for Time {
+    fn sub_assign(&mut self, rhs: Self) {
+        *self = *self - rhs;
+    }
+}
+
+impl Neg for Time {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        Self(self.0.saturating_neg())
+    }
+}
+
+impl Mul<i64> for Time {
+    type Output = Self;
+
+    fn mul(self, rhs: i64) -> Self {
+        Self(self.0.saturating_mul(rhs))
+    }
+}
+
+/// Dividing by zero saturates towards the sign of the time, zero stays zero.
+impl Div<i64> for Time {
+    type Output = Self;
+
+    fn div(self, rhs: i64) -> Self {
+        match self.0.checked_div(rhs) {
+            Some(ticks) => Self(ticks),
+            None if self.0 == 0 => Self::ZERO,
+            None if (self.0 < 0) == (rhs < 0) => Self::MAX,
+            None => Self::MIN,
+        }
+    }
+}
+
+/// Formats as `[-]HH:MM:SS.mmm`, the precision (at most 3) sets the number of
+/// fractional digits, so `{:.0}` gives `HH:MM:SS`.
+impl fmt::Display for Time {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let sign = if self.is_negative() { "-" } else { "" };
+        let millis = self.abs().as_millis().unsigned_abs();
+        write!(
+            f,
+            "{}{:02}:{:02}:{:02}",
+            sign,
+            millis / 3_600_000,
+            millis / 60_000 % 60,
+            millis / 1000 % 60,
+        )?;
+        match f.precision().unwrap_or(3).min(3) {
+            0 => Ok(()),
+            digits => {
+                let fraction = millis % 1000 / 10u64.pow(3 - digits as u32);
+                write!(f, ".{:0digits$}", fraction)
+            }
+        }
+    }
+}
+
+/// A frame rate as an exact fraction, e.g. `30000/1001` for NTSC.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(try_from = "FrameRateData"))]
+pub struct FrameRate {
+    numerator: u32,
+    denominator: u32,
+    drop_frame: bool,
+}
+
+impl FrameRate {
+    pub const FPS_24: Self = Self::new(24, 1);
+    pub const FPS_25: Self = Self::new(25, 1);
+    pub const FPS_29_97: Self = Self::new(30000, 1001);
+    pub const FPS_29_97_DF: Self = Self::new(30000, 1001).with_drop_frame(true);
+    pub const FPS_30: Self = Self::new(30, 1);
+    pub const FPS_60: Self = Self::new(60, 1);
+
+    /// Panics if either part is zero.
+    pub const fn new(numerator: u32, denominator: u32) -> Self {
+        assert!(numerator != 0 && denominator != 0, "invalid frame rate");
+        Self {
+            numerator,
+            denominator,
+            drop_frame: false,
+        }
+    }
+
+    /// Drop-frame only affects how timecode is counted, the frames themselves stay evenly spaced.
+    pub const fn with_drop_frame(mut self, drop_frame: bool) -> Self {
+        self.drop_frame = drop_frame;
+        self
+    }
+
+    pub const fn is_drop_frame(self) -> bool {
+        self.drop_frame
+    }
+
+    pub const fn numerator(self) -> u32 {
+        self.numerator
+    }
+
+    pub const fn denominator(self) -> u32 {
+        self.denominator
+    }
+
+    pub fn as_fps(self) -> f64 {
+        self.numerator as f64 / self.denominator as f64
+    }
+
+    /// Length of a single frame, exact for rates that divide [`Time::TICKS_PER_SECOND`].
+    pub fn frame_duration(self) -> Time {
+        Time::from_frames(1, self)
+    }
+}
+
+impl Default for FrameRate {
+    fn default() -> Self {
+        Self::FPS_30
+    }
+}
+
+/// Saved form of a [`FrameRate`], checked for zero parts when loading.
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+struct FrameRateData {
+    numerator: u32,
+    denominator: u32,
+    drop_frame: bool,
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<FrameRateData> for FrameRate {
+    type Error = &'static str;
+
+    fn try_from(data: FrameRateData) -> Result<Self, Self::Error> {
+        if data.numerator == 0 || data.denominator == 0 {
+            return Err("invalid frame rate");
+        }
+        Ok(Self::new(data.numerator, data.denominator).with_drop_frame(data.drop_frame))
+    }
+}
+
+/// How times are shown to, and typed by, the user.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TimeFormat {
+    /// SMPTE `HH:MM:SS:FF`, `HH:MM:SS;FF` for drop-frame rates.
+    #[default]
+    Timecode,
+    Frames,
+    Seconds,
+    Milliseconds,
+}
+
+impl TimeFormat {
+    pub const ALL: [Self; 4] = [
+        Self::Timecode,
+        Self::Frames,
+        Self::Seconds,
+        Self::Milliseconds,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Timecode => "Timecode",
+            Self::Frames => "Frames",
+            Self::Seconds => "Seconds",
+            Self::Milliseconds => "Milliseconds",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseTimeError {
+    Empty,
+    InvalidNumber(String),
+    /// A timecode with more than four fields.
+    TooManyFields,
+    /// A timecode field exceeds its range, e.g. 60 seconds or 30 frames at 30 fps.
+    OutOfRange(&'static str),
+    /// A drop-frame timecode naming one of the skipped frame numbers.
+    DroppedFrame,
+}
+
+impl fmt::Display for ParseTimeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Empty => write!(f, "no time entered"),
+            Self::InvalidNumber(number) => write!(f, "'{}' is not a valid number", number),
+            Self::TooManyFields => write!(f, "timecode has more than four fields"),
+            Self::OutOfRange(field) => write!(f, "{} out of range", field),
+            Self::DroppedFrame => write!(f, "frame does not exist in drop-frame timecode"),
+        }
+    }
+}
+
+impl Error for ParseTimeError {}
+
+/// Frames dropped from the count each minute, 2 for 29.97 and 4 for 59.94.
+fn dropped_frames(rate: FrameRate) -> i64 {
+    if rate.is_drop_frame() {
+        nominal_fps(rate) / 15
+    } else {
+        0
+    }
+}
+
+/// The frame count per timecode second, 30 for 29.97.
+fn nominal_fps(rate: FrameRate) -> i64 {
+    (rate.as_fps().round() as i64).max(1)
+}
+
+impl Time {
+    pub fn format(self, format: TimeFormat, rate: FrameRate) -> String {
+        let sign = if self.is_negative() { "-" } else { "" };
+        match format {
+            TimeFormat::Timecode => {
+                let fps = nominal_fps(rate);
+                let drop = dropped_frames(rate);
+                let mut frame = self.abs().frame(rate);
+                if drop > 0 {
+                    // Renumber so the skipped frame numbers are counted back in.
+                    let per_ten_minutes = fps * 600 - drop * 9;
+                    let per_minute = fps * 60 - drop;
+                    let tens = frame / per_ten_minutes;
+                    let rest = frame % per_ten_minutes;
+                    frame += drop * 9 * tens;
+                    if rest > drop {
+                        frame += drop * ((rest - drop) / per_minute);
+                    }
+                }
+                format!(
+                    "{}{:02}:{:02}:{:02}{}{:02}",
+                    sign,
+                    frame / (fps * 3600),
+                    frame / (fps * 60) % 60,
+                    frame / fps % 60,
+                    if drop > 0 { ';' } else { ':' },
+                    frame % fps
+                )
+            }
+            TimeFormat::Frames => self.frame(rate).to_string(),
+            TimeFormat::Seconds => format!("{:.3} s", self.as_seconds()),
+            TimeFormat::Milliseconds => format!("{} ms", self.as_millis()),
+        }
+    }
+
+    /// Parses user input in `format`. Timecode fields are right aligned, so `5:00` is
+    /// five seconds, and units written by [`Time::format`] are accepted.
+    pub fn parse(input: &str, format: TimeFormat, rate: FrameRate) -> Result<Self, ParseTimeError> {
+        let input = input.trim();
+        if input.is_empty() {
+            return Err(ParseTimeError::Empty);
+        }
+        let (negative, input) = match input.strip_prefix('-') {
+            Some(rest) => (true, rest.trim_start()),
+            None => (false, input),
+        };
+
+        let time = match format {
+            TimeFormat::Timecode => Self::parse_timecode(input, rate)?,
+            TimeFormat::Frames => Self::checked_from_frames(parse_number(input)?, rate)
+                .ok_or(ParseTimeError::OutOfRange("frames"))?,
+            TimeFormat::Seconds => {
+                let number = input.trim_end_matches('s').trim_end();
+                let seconds: f64 = number
+                    .parse()
+                    .ok()
+                    .filter(|s: &f64| s.is_finite() && *s >= 0.0)
+                    .ok_or_else(|| ParseTimeError::InvalidNumber(number.to_string()))?;
+                Self::from_seconds(seconds)
+            }
+            TimeFormat::Milliseconds => {
+                Self::from_millis(parse_number(input.trim_end_matches("ms").trim_end())?)
+            }
+        };
+        Ok(if negative { -time } else { time })
+    }
+
+    fn parse_timecode(input: &str, rate: FrameRate) -> Result<Self, ParseTimeError> {
+        let fields = input
+            .split([':', ';'])
+            .map(parse_number)
+            .collect::<Result<Vec<_>, _>>()?;
+        if fields.len() > 4 {
+            return Err(ParseTimeError::TooManyFields);
+        }
+        let field = |from_end: usize| {
+            fields
+                .len()
+                .checked_sub(from_end + 1)
+                .map_or(0, |i| fields[i])
+        };
+        let (hours, minutes, seconds, frames) = (field(3), field(2), field(1), field(0));
+
+        let fps = nominal_fps(rate);
+        // Only the leading field may overflow, so `90` alone is 90 frames.
+        if fields.len() > 1 && frames >= fps {
+            return Err(ParseTimeError::OutOfRange("frames"));
+        }
+        if fields.len() > 2 && seconds >= 60 {
+            return Err(ParseTimeError::OutOfRange("seconds"));
+        }
+        if fields.len() > 3 && minutes >= 60 {
+            return Err(ParseTimeError::OutOfRange("minutes"));
+        }
+
+        let drop = dropped_frames(rate);
+        let total_minutes = hours * 60 + minutes;
+        if drop > 0 && fields.len() > 2 && seconds == 0 && frames < drop && total_minutes % 10 != 0
+        {
+            return Err(ParseTimeError::DroppedFrame);
+        }
+        let frame = ((total_minutes * 60 + seconds) as i128 * fps as i128 + frames as i128)
+            - (drop * (total_minutes - total_minutes / 10)) as i128;
+        // Only the leading field is unbounded.
+        let leading = ["frames", "seconds", "minutes", "hours"][fields.len() - 1];
+        i64::try_from(frame)
+            .ok()
+            .and_then(|frame| Self::checked_from_frames(frame, rate))
+            .ok_or(ParseTimeError::OutOfRange(leading))
+    }
+}
+
+fn parse_number(input: &str) -> Result<i64, ParseTimeError> {
+    let input = input.trim();
+    input
+        .parse::<u32>()
+        .map(i64::from)
+        .map_err(|_| ParseTimeError::InvalidNumber(input.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frame_arithmetic_is_exact() {
+        let ntsc = FrameRate::FPS_29_97;
+        // 30000 frames at 30000/1001 fps last exactly 1001 seconds.
+        assert_eq!(
+            Time::from_frames(30_000, ntsc),
+            Time::from_millis(1_001_000)
+        );
+        let mut time = Time::ZERO;
+        for _ in 0..30_000 {
+            time += ntsc.frame_duration();
+        }
+        assert_eq!(time, Time::from_frames(30_000, ntsc));
+        assert_eq!(Time::from_millis(40).frame(FrameRate::FPS_25), 1);
+        assert_eq!(Time::from_millis(39).frame(FrameRate::FPS_25), 0);
+    }
+
+    #[test]
+    fn frames_round_towards_negative_infinity() {
+        let rate = FrameRate::FPS_30;
+        assert_eq!(Time::from_millis(-1).frame(rate), -1);
+        assert_eq!(
+            Time::from_millis(-1).floor_to_frame(rate),
+            Time::from_frames(-1, rate)
+        );
+        assert_eq!(Time::from_millis(-1).as_millis(), -1);
+        assert_eq!(Time::from_ticks(-1).as_millis(), -1);
+    }
+
+    #[test]
+    fn rounds_to_the_closest_frame() {
+        let rate = FrameRate::FPS_25;
+        assert_eq!(Time::from_millis(19).round_to_frame(rate), Time::ZERO);
+        assert_eq!(
+            Time::from_millis(20).round_to_frame(rate),
+            Time::from_millis(40)
+        );
+        assert_eq!(
+            Time::from_millis(-21).round_to_frame(rate),
+            Time::from_millis(-40)
+        );
+    }
+
+    #[test]
+    fn arithmetic_saturates() {
+        assert_eq!(Time::MAX + Time::from_ticks(1), Time::MAX);
+        assert_eq!(Time::MIN - Time::from_ticks(1), Time::MIN);
+        assert_eq!(-Time::MIN, Time::MAX);
+        assert_eq!(Time::MAX * 2, Time::MAX);
+        assert_eq!(Time::MIN / -1, Time::MAX);
+        assert_eq!(Time::from_millis(1) / 0, Time::MAX);
+        assert_eq!(Time::from_millis(-1) / 0, Time::MIN);
+        assert_eq!(Time::ZERO / 0, Time::ZERO);
+        assert_eq!(Time::from_millis(-10) / -2, Time::from_millis(5));
+    }
+
+    #[test]
+    fn displays_with_requested_precision() {
+        let time = Time::from_millis(3_723_456);
+        assert_eq!(time.to_string(), "01:02:03.456");
+        assert_eq!(format!("{:.1}", time), "01:02:03.4");
+        assert_eq!(format!("{:.0}", -time), "-01:02:03");
+    }
+
+    fn timecode(frame: i64, rate: FrameRate) -> String {
+        Time::from_frames(frame, rate).format(TimeFormat::Timecode, rate)
+    }
+
+    fn parse_timecode(input: &str, rate: FrameRate) -> Result<Time, ParseTimeError> {
+        Time::parse(input, TimeFormat::Timecode, rate)
+    }
+
+    #[test]
+    fn formats_timecode() {
+        assert_eq!(timecode(0, FrameRate::FPS_30), "00:00:00:00");
+        assert_eq!(timecode(109_835, FrameRate::FPS_30), "01:01:01:05");
+        assert_eq!(timecode(-31, FrameRate::FPS_30), "-00:00:01:01");
+        assert_eq!(timecode(49, FrameRate::FPS_24), "00:00:02:01");
+    }
+
+    #[test]
+    fn formats_drop_frame_timecode() {
+        let rate = FrameRate::FPS_29_97_DF;
+        assert_eq!(timecode(1799, rate), "00:00:59;29");
+        // Frames 00 and 01 of every minute are skipped, except every tenth minute.
+        assert_eq!(timecode(1800, rate), "00:01:00;02");
+        assert_eq!(timecode(17_981, rate), "00:09:59;29");
+        assert_eq!(timecode(17_982, rate), "00:10:00;00");
+        assert_eq!(timecode(107_892, rate), "01:00:00;00");
+    }
+
+    #[test]
+    fn parses_what_it_formats() {
+        for rate in [
+            FrameRate::FPS_24,
+            FrameRate::FPS_29_97,
+            FrameRate::FPS_29_97_DF,
+            FrameRate::FPS_60,
+        ] {
+            for frame in (0..40_000).step_by(7).chain(17_975..17_990) {
+                let time = Time::from_frames(frame, rate);
+                // Seconds and milliseconds are rounded, only the frame formats are exact.
+                for format in [TimeFormat::Timecode, TimeFormat::Frames] {
+                    let text = time.format(format, rate);
+                    assert_eq!(Time::parse(&text, format, rate), Ok(time), "{}", text);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn parses_right_aligned_timecode_fields() {
+        let rate = FrameRate::FPS_30;
+        assert_eq!(parse_timecode("90", rate), Ok(Time::from_frames(90, rate)));
+        assert_eq!(
+            parse_timecode("5:00", rate),
+            Ok(Time::from_frames(150, rate))
+        );
+        assert_eq!(
+            parse_timecode("-1:00", rate),
+            Ok(Time::from_frames(-30, rate))
+        );
+        assert_eq!(
+            parse_timecode("1:00:00:00", rate),
+            Ok(Time::from_frames(108_000, rate))
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_timecode() {
+        let rate = FrameRate::FPS_30;
+        assert_eq!(parse_timecode("", rate), Err(ParseTimeError::Empty));
+        assert_eq!(
+            parse_timecode("1:2:3:4:5", rate),
+            Err(ParseTimeError::TooManyFields)
+        );
+        assert_eq!(
+            parse_timecode("00:00:60:00", rate),
+            Err(ParseTimeError::OutOfRange("seconds"))
+        );
+        assert_eq!(
+            parse_timecode("00:00:00:30", rate),
+            Err(ParseTimeError::OutOfRange("frames"))
+        );
+        assert_eq!(
+            parse_timecode("00:01:00;00", FrameRate::FPS_29_97_DF),
+            Err(ParseTimeError::DroppedFrame)
+        );
+        assert!(matches!(
+            parse_timecode("1:x", rate),
+            Err(ParseTimeError::InvalidNumber(_))
+        ));
+    }
+
+    #[test]
+    fn rejects_timecode_beyond_time_range() {
+        assert_eq!(
+            parse_timecode("4000000000:00:00:00", FrameRate::FPS_30),
+            Err(ParseTimeError::OutOfRange("hours"))
+        );
+        assert_eq!(Time::from_frames(i64::MAX, FrameRate::FPS_30), Time::MAX);
+        assert_eq!(Time::from_frames(i64::MIN, FrameRate::FPS_30), Time::MIN);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn frame_rate_with_zero_part_does_not_load() {
+        use serde_value::Value;
+
+        let rate = |numerator: u32, denominator: u32| {
+            Value::Map(
+                [
+                    ("numerator", Value::U32(numerator)),
+                    ("denominator", Value::U32(denominator)),
+                    ("drop_frame", Value::Bool(false)),
+                ]
+                .into_iter()
+                .map(|(k, v)| (Value::String(k.to_string()), v))
+                .collect(),
+            )
+        };
+        assert_eq!(
+            rate(24, 1).deserialize_into::<FrameRate>().unwrap(),
+            FrameRate::FPS_24
+        );
+        assert!(rate(0, 1).deserialize_into::<FrameRate>().is_err());
+        assert!(rate(30, 0).deserialize_into::<FrameRate>().is_err());
+    }
+}
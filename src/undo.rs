@@ -0,0 +1,579 @@
+use std::{any::Any, rc::Rc};
+
+use crate::{KeyId, KeyRef, Time, Timeline, TimelineEvent, Track, TrackEntry, TrackId};
+
+/// Undo steps kept by the built-in history, the oldest are dropped first.
+const MAX_UNDO_STEPS: usize = 500;
+
+/// Snapshot of a key from [`Track::key_state`].
+pub type KeyState = Rc<dyn Any>;
+
+/// An undoable edit of a [`Timeline`], possibly a group of edits undone as one step.
+pub struct Command {
+    edit: Edit,
+}
+
+impl Command {
+    /// Short description for undo menus, e.g. "Move key".
+    pub fn name(&self) -> &'static str {
+        self.edit.name()
+    }
+}
+
+enum Edit {
+    AddKey {
+        key: KeyRef,
+        state: KeyState,
+    },
+    DeleteKey {
+        key: KeyRef,
+        state: KeyState,
+    },
+    MoveKey {
+        key: KeyRef,
+        from: Time,
+        to: Time,
+    },
+    ChangeKey {
+        key: KeyRef,
+        before: KeyState,
+        after: KeyState,
+    },
+    /// `track` holds the track while it is not in the timeline.
+    AddTrack {
+        id: TrackId,
+        index: usize,
+        track: Option<Box<dyn Track>>,
+    },
+    RemoveTrack {
+        id: TrackId,
+        index: usize,
+        track: Option<Box<dyn Track>>,
+    },
+    MoveTrack {
+        id: TrackId,
+        from: usize,
+        to: usize,
+    },
+    /// In and out point before and after the change.
+    PlaybackRange {
+        before: (Option<Time>, Option<Time>),
+        after: (Option<Time>, Option<Time>),
+    },
+    Group(Vec<Edit>),
+}
+
+impl Edit {
+    /// The key a key edit applies to.
+    fn key(&self) -> Option<KeyRef> {
+        match self {
+            Self::AddKey { key, .. }
+            | Self::DeleteKey { key, .. }
+            | Self::MoveKey { key, .. }
+            | Self::ChangeKey { key, .. } => Some(*key),
+            _ => None,
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Self::AddKey { .. } => "Add key",
+            Self::DeleteKey { .. } => "Delete key",
+            Self::MoveKey { .. } => "Move key",
+            Self::ChangeKey { .. } => "Edit key",
+            Self::AddTrack { .. } => "Add track",
+            Self::RemoveTrack { .. } => "Remove track",
+            Self::MoveTrack { .. } => "Move track",
+            Self::PlaybackRange { .. } => "Change playback range",
+            Self::Group(edits) => edits.first().map_or("Edit", Self::name),
+        }
+    }
+}
+
+/// Lets the host keep timeline edits in its own undo stack instead of the built-in history,
+/// see [`Timeline::set_undo_host`].
+pub trait UndoHost {
+    /// Takes an edit that was just made. Revert it with [`Timeline::undo_command`] and apply
+    /// it again with [`Timeline::redo_command`].
+    fn record(&mut self, command: Command);
+    /// Ctrl+Z was pressed in the timeline window or [`Timeline::undo`] was called.
+    fn undo_requested(&mut self) {}
+    /// Ctrl+Shift+Z was pressed in the timeline window or [`Timeline::redo`] was called.
+    fn redo_requested(&mut self) {}
+}
+
+#[derive(Default)]
+pub(crate) struct History {
+    undo: Vec<Command>,
+    redo: Vec<Command>,
+    /// Edits of the open group and how many times it was begun.
+    group: Vec<Edit>,
+    group_depth: usize,
+    host: Option<Box<dyn UndoHost>>,
+    track_edits: bool,
+}
+
+impl Timeline {
+    /// Reverts the last edit, `false` if there is nothing to undo or an [`UndoHost`] is set.
+    pub fn undo(&mut self) -> bool {
+        if let Some(host) = &mut self.history.host {
+            host.undo_requested();
+            return false;
+        }
+        let Some(mut command) = self.history.undo.pop() else {
+            return false;
+        };
+        self.undo_command(&mut command);
+        self.history.redo.push(command);
+        true
+    }
+
+    /// Applies the last undone edit again, `false` if there is nothing to redo or an
+    /// [`UndoHost`] is set.
+    pub fn redo(&mut self) -> bool {
+        if let Some(host) = &mut self.history.host {
+            host.redo_requested();
+            return false;
+        }
+        let Some(mut command) = self.history.redo.pop() else {
+            return false;
+        };
+        self.redo_command(&mut command);
+        self.history.undo.push(command);
+        true
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.history.undo.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.history.redo.is_empty()
+    }
+
+    /// Name of the edit [`Timeline::undo`] would revert.
+    pub fn undo_name(&self) -> Option<&'static str> {
+        self.history.undo.last().map(Command::name)
+    }
+
+    pub fn redo_name(&self) -> Option<&'static str> {
+        self.history.redo.last().map(Command::name)
+    }
+
+    /// Forgets all edits, e.g. after building the timeline for a newly opened document.
+    pub fn clear_history(&mut self) {
+        self.history.undo.clear();
+        self.history.redo.clear();
+        self.history.group.clear();
+        self.history.group_depth = 0;
+    }
+
+    /// Collects the following edits into a single undo step until the matching
+    /// [`Timeline::end_group`], groups can be nested.
+    pub fn begin_group(&mut self) {
+        self.history.group_depth += 1;
+    }
+
+    pub fn end_group(&mut self) {
+        if self.history.group_depth == 0 {
+            return;
+        }
+        self.history.group_depth -= 1;
+        if self.history.group_depth == 0 {
+            let mut edits = std::mem::take(&mut self.history.group);
+            match edits.len() {
+                0 => {}
+                1 => self.push_command(Command {
+                    edit: edits.remove(0),
+                }),
+                _ => self.push_command(Command {
+                    edit: Edit::Group(edits),
+                }),
+            }
+        }
+    }
+
+    /// Sends edits to `host` instead of the built-in history, which is cleared.
+    pub fn set_undo_host(&mut self, host: impl UndoHost + 'static) {
+        self.clear_history();
+        self.history.host = Some(Box::new(host));
+    }
+
+    /// Whether [`Timeline::add_track`], [`Timeline::insert_track`] and
+    /// [`Timeline::remove_track`] record undo steps. Off by default, so the tracks a host sets
+    /// up can't be undone.
+    pub fn set_undo_track_edits(&mut self, record: bool) {
+        self.history.track_edits = record;
+    }
+
+    pub fn remove_undo_host(&mut self) -> Option<Box<dyn UndoHost>> {
+        self.history.host.take()
+    }
+
+    /// Reverts a command recorded by an [`UndoHost`].
+    pub fn undo_command(&mut self, command: &mut Command) {
+        self.apply_edit(&mut command.edit, false);
+    }
+
+    /// Applies a command recorded by an [`UndoHost`] again after it was undone.
+    pub fn redo_command(&mut self, command: &mut Command) {
+        self.apply_edit(&mut command.edit, true);
+    }
+
+    /// Edits a key through its track, recording the change when the edit returns `true`,
+    /// e.g. `timeline.edit_key(key, |t: &mut KeyFrameTrack<f32>, id| t.set_key_value(id, 1.0))`.
+    pub fn edit_key<T: Track>(
+        &mut self,
+        key: KeyRef,
+        edit: impl FnOnce(&mut T, KeyId) -> bool,
+    ) -> bool {
+        let before = self.track(key.track).and_then(|t| t.key_state(key.key));
+        let Some(track) = self.track_as_mut::<T>(key.track) else {
+            return false;
+        };
+        if !edit(track, key.key) {
+            return false;
+        }
+        let after = self.track(key.track).and_then(|t| t.key_state(key.key));
+        if let (Some(before), Some(after)) = (before, after) {
+            self.record(Edit::ChangeKey { key, before, after });
+        }
+        true
+    }
+
+    pub(crate) fn record_add_key(&mut self, key: KeyRef, replaced: Option<KeyState>) {
+        let Some(state) = self.track(key.track).and_then(|t| t.key_state(key.key)) else {
+            return;
+        };
+        self.record(match replaced {
+            Some(before) => Edit::ChangeKey {
+                key,
+                before,
+                after: state,
+            },
+            None => Edit::AddKey { key, state },
+        });
+    }
+
+    pub(crate) fn record_delete_key(&mut self, key: KeyRef, state: KeyState) {
+        self.record(Edit::DeleteKey { key, state });
+    }
+
+    pub(crate) fn record_change_key(&mut self, key: KeyRef, before: KeyState, after: KeyState) {
+        self.record(Edit::ChangeKey { key, before, after });
+    }
+
+    pub(crate) fn record_move_key(&mut self, key: KeyRef, from: Time, to: Time) {
+        self.record(Edit::MoveKey { key, from, to });
+    }
+
+    pub(crate) fn record_add_track(&mut self, id: TrackId, index: usize) {
+        if !self.history.track_edits {
+            return;
+        }
+        self.record(Edit::AddTrack {
+            id,
+            index,
+            track: None,
+        });
+    }
+
+    pub(crate) fn record_remove_track(&mut self, id: TrackId, index: usize, track: Box<dyn Track>) {
+        if !self.history.track_edits {
+            return;
+        }
+        self.record(Edit::RemoveTrack {
+            id,
+            index,
+            track: Some(track),
+        });
+    }
+
+    pub(crate) fn record_move_track(&mut self, id: TrackId, from: usize, to: usize) {
+        self.record(Edit::MoveTrack { id, from, to });
+    }
+
+    pub(crate) fn record_playback_range(&mut self, before: (Option<Time>, Option<Time>)) {
+        let after = (self.in_point, self.out_point);
+        if before != after {
+            self.record(Edit::PlaybackRange { before, after });
+        }
+    }
+
+    fn record(&mut self, edit: Edit) {
+        if self.history.group_depth == 0 {
+            self.push_command(Command { edit });
+            return;
+        }
+        // A drag moves its keys every frame, keep one move per key.
+        if let Edit::MoveKey { key, to, .. } = &edit {
+            let merged = self.history.group.iter_mut().find_map(|e| match e {
+                Edit::MoveKey { key: k, to, .. } if k == key => Some(to),
+                _ => None,
+            });
+            if let Some(merged) = merged {
+                *merged = *to;
+                return;
+            }
+        }
+        // Likewise for lasting keys, as long as nothing else happened to the key since.
+        if let Edit::ChangeKey { key, after, .. } = &edit {
+            let last = self
+                .history
+                .group
+                .iter_mut()
+                .rev()
+                .find(|e| e.key() == Some(*key));
+            if let Some(Edit::ChangeKey { after: merged, .. }) = last {
+                *merged = after.clone();
+                return;
+            }
+        }
+        self.history.group.push(edit);
+    }
+
+    fn push_command(&mut self, command: Command) {
+        match &mut self.history.host {
+            Some(host) => host.record(command),
+            None => {
+                self.history.undo.push(command);
+                self.history.redo.clear();
+                if self.history.undo.len() > MAX_UNDO_STEPS {
+                    self.history.undo.remove(0);
+                }
+            }
+        }
+    }
+
+    /// Applies `edit` when `redo` is set, reverts it otherwise. Returns `false` if a key move
+    /// was refused, e.g. because another key is in the way.
+    fn apply_edit(&mut self, edit: &mut Edit, redo: bool) -> bool {
+        match edit {
+            Edit::AddKey { key, state } => self.set_key_present(*key, state, redo),
+            Edit::DeleteKey { key, state } => self.set_key_present(*key, state, !redo),
+            Edit::MoveKey { key, from, to } => {
+                let (from, to) = if redo { (*from, *to) } else { (*to, *from) };
+                if !self
+                    .track_mut(key.track)
+                    .is_some_and(|t| t.move_key(key.key, to))
+                {
+                    return false;
+                }
+                self.emit(TimelineEvent::KeyMoved {
+                    key: *key,
+                    from,
+                    to,
+                });
+            }
+            Edit::ChangeKey { key, before, after } => {
+                let state = if redo { after } else { before };
+                if self
+                    .track_mut(key.track)
+                    .is_some_and(|t| t.restore_key(state.as_ref()))
+                {
+                    self.emit(TimelineEvent::KeyChanged(*key));
+                }
+            }
+            Edit::AddTrack { id, index, track } => self.set_track_present(*id, *index, track, redo),
+            Edit::RemoveTrack { id, index, track } => {
+                self.set_track_present(*id, *index, track, !redo)
+            }
+            Edit::MoveTrack { id, from, to } => {
+                let (from, to) = if redo { (*from, *to) } else { (*to, *from) };
+                if let Some(index) = self.track_index(*id) {
+                    let entry = self.tracks.remove(index);
+                    self.tracks.insert(to.min(self.tracks.len()), entry);
+                    self.emit(TimelineEvent::TrackMoved {
+                        track: *id,
+                        from,
+                        to,
+                    });
+                }
+            }
+            Edit::PlaybackRange { before, after } => {
+                (self.in_point, self.out_point) = if redo { *after } else { *before };
+            }
+            Edit::Group(edits) => {
+                let mut pending = if redo {
+                    edits.iter_mut().collect::<Vec<_>>()
+                } else {
+                    edits.iter_mut().rev().collect()
+                };
+                // A key blocked by another key of the group moves once the other one made room.
+                while !pending.is_empty() {
+                    let count = pending.len();
+                    pending.retain_mut(|e| !self.apply_edit(e, redo));
+                    if pending.len() == count {
+                        return false;
+                    }
+                }
+            }
+        }
+        true
+    }
+
+    fn set_key_present(&mut self, key: KeyRef, state: &KeyState, present: bool) {
+        let Some(track) = self.track_mut(key.track) else {
+            return;
+        };
+        if present {
+            if track.restore_key(state.as_ref()) {
+                self.emit(TimelineEvent::KeyAdded(key));
+            }
+        } else if track.delete_key(key.key) {
+            self.deselect_key(key);
+            self.emit(TimelineEvent::KeyDeleted(key));
+        }
+    }
+
+    fn set_track_present(
+        &mut self,
+        id: TrackId,
+        index: usize,
+        track: &mut Option<Box<dyn Track>>,
+        present: bool,
+    ) {
+        if present {
+            if let Some(track) = track.take() {
+                let index = index.min(self.tracks.len());
+                self.tracks.insert(index, TrackEntry { id, track });
+                self.emit(TimelineEvent::TrackAdded(id));
+            }
+        } else if let Some(index) = self.track_index(id) {
+            self.selection.retain(|r| r.track != id);
+            *track = Some(self.tracks.remove(index).track);
+            self.emit(TimelineEvent::TrackRemoved(id));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, rc::Rc};
+
+    use super::{Command, UndoHost};
+    use crate::{ClipTrack, KeyRef, Time, Timeline, TimelineEvent};
+
+    fn clip_starts(timeline: &Timeline, track: crate::TrackId) -> Vec<Time> {
+        let track = timeline.track_as::<ClipTrack>(track).unwrap();
+        track.clips().iter().map(|c| c.start()).collect()
+    }
+
+    #[test]
+    fn undo_restores_clips_dragged_against_each_other() {
+        let mut timeline = Timeline::new("test");
+        let mut clips = ClipTrack::new("clips");
+        let a = clips.insert_clip(Time::ZERO, Time::from_millis(2000), "a", [1.0; 4]);
+        let b = clips.insert_clip(
+            Time::from_millis(2000),
+            Time::from_millis(2000),
+            "b",
+            [1.0; 4],
+        );
+        let track = timeline.add_track(clips);
+        let (a, b) = (
+            KeyRef::new(track, a.unwrap()),
+            KeyRef::new(track, b.unwrap()),
+        );
+
+        // Like a drag, which moves whichever clip has room first.
+        timeline.begin_group();
+        assert!(timeline.move_key(b, Time::from_millis(3000)));
+        assert!(timeline.move_key(a, Time::from_millis(1000)));
+        assert!(timeline.move_key(a, Time::from_millis(-1000)));
+        assert!(timeline.move_key(b, Time::from_millis(1000)));
+        timeline.end_group();
+
+        assert!(timeline.undo());
+        assert_eq!(
+            clip_starts(&timeline, track),
+            [Time::ZERO, Time::from_millis(2000)]
+        );
+        assert!(timeline.redo());
+        assert_eq!(
+            clip_starts(&timeline, track),
+            [Time::from_millis(-1000), Time::from_millis(1000)]
+        );
+    }
+
+    #[test]
+    fn drag_of_keyframes_undoes_as_one_step() {
+        let mut timeline = Timeline::new("test");
+        let mut keys = crate::KeyFrameTrack::<f32>::new("keys");
+        let key = keys.insert_key(Time::ZERO, 1.0);
+        let track = timeline.add_track(keys);
+        let key = KeyRef::new(track, key);
+
+        timeline.begin_group();
+        for millis in [100, 200, 300] {
+            assert!(timeline.move_key(key, Time::from_millis(millis)));
+        }
+        timeline.end_group();
+        assert_eq!(timeline.undo_name(), Some("Move key"));
+        assert!(timeline.undo());
+        assert!(!timeline.can_undo());
+        assert_eq!(
+            timeline.track(track).unwrap().key_times(),
+            [(key.key, Time::ZERO)]
+        );
+    }
+
+    #[test]
+    fn track_edits_are_recorded_once_enabled() {
+        let mut timeline = Timeline::new("test");
+        let track = timeline.add_track(ClipTrack::new("clips"));
+        assert!(!timeline.can_undo());
+
+        timeline.set_undo_track_edits(true);
+        assert!(timeline.remove_track(track));
+        assert_eq!(timeline.undo_name(), Some("Remove track"));
+        assert!(timeline.undo());
+        assert!(timeline.track(track).is_some());
+    }
+
+    #[test]
+    fn host_undo_is_reported_by_the_next_draw() {
+        struct Host(Rc<RefCell<Vec<Command>>>);
+        impl UndoHost for Host {
+            fn record(&mut self, command: Command) {
+                self.0.borrow_mut().push(command);
+            }
+        }
+
+        let mut timeline = Timeline::new("test");
+        let track = timeline.add_track(ClipTrack::new("clips"));
+        let commands = Rc::new(RefCell::new(Vec::new()));
+        timeline.set_undo_host(Host(commands.clone()));
+        let key = timeline.add_key(track, Time::ZERO).unwrap();
+
+        let mut command = commands.borrow_mut().pop().unwrap();
+        timeline.undo_command(&mut command);
+        assert_eq!(timeline.events, [TimelineEvent::KeyDeleted(key)]);
+    }
+
+    #[test]
+    fn redo_moves_keys_once_others_made_room() {
+        let mut timeline = Timeline::new("test");
+        let mut keys = crate::KeyFrameTrack::<f32>::new("keys");
+        let a = keys.insert_key(Time::ZERO, 0.0);
+        let b = keys.insert_key(Time::from_millis(100), 1.0);
+        let track = timeline.add_track(keys);
+        let [a, b] = [a, b].map(|key| KeyRef::new(track, key));
+
+        timeline.begin_group();
+        assert!(timeline.move_key(a, Time::from_millis(50)));
+        assert!(timeline.move_key(b, Time::from_millis(200)));
+        assert!(timeline.move_key(a, Time::from_millis(100)));
+        timeline.end_group();
+        // Redoing the merged move of `a` first finds `b` still in the way.
+        assert!(timeline.undo());
+        assert!(timeline.redo());
+        assert_eq!(
+            timeline.track(track).unwrap().key_times(),
+            [
+                (a.key, Time::from_millis(100)),
+                (b.key, Time::from_millis(200))
+            ]
+        );
+    }
+}
@@ -0,0 +1,1671 @@
+use imgui::{
+    sys::{
+        ImGuiKey_C, ImGuiKey_Delete, ImGuiKey_I, ImGuiKey_J, ImGuiKey_K, ImGuiKey_L, ImGuiKey_M,
+        ImGuiKey_O, ImGuiKey_S, ImGuiKey_Space, ImGuiKey_V, ImGuiKey_X, ImGuiKey_Z,
+    },
+    Drag, MouseButton, MouseCursor, StyleColor, StyleVar,
+};
+
+use crate::{
+    time_from_offset, AsArray, EditorMode, FrameRate, KeyId, KeyRef, KeyState, Marker,
+    ParseTimeError, PlaybackMode, Point, Response, Time, TimeFormat, Timeline, TimelineEvent,
+    Track, TrackId, TrackView, DEBUG_DRAW,
+};
+
+pub(crate) const SECOND_WIDTH: f32 = 250.0;
+/// Frames get their own ruler ticks once they are at least this many pixels apart.
+const MIN_FRAME_TICK_SPACING: f32 = 5.0;
+const MIN_MINOR_TICK_SPACING: f32 = 5.0;
+/// Free space between two ruler labels.
+const MIN_LABEL_GAP: f32 = 10.0;
+pub(crate) const KEY_HIT_RADIUS: f32 = 6.0;
+const MIN_GRAPH_HEIGHT: f32 = 50.0;
+/// How far from the edge of a lasting key the edge can be grabbed.
+const EDGE_HIT_WIDTH: f32 = 4.0;
+pub(crate) const MIN_TIME_SCALE: f32 = 0.01;
+pub(crate) const MAX_TIME_SCALE: f32 = 10.0;
+/// Zoom factor per mouse wheel notch.
+pub(crate) const ZOOM_STEP: f32 = 1.15;
+/// Pixels panned per mouse wheel notch.
+const PAN_STEP: f32 = 60.0;
+const MAX_PLAYBACK_SPEED: f32 = 8.0;
+
+const FRAME_RATE_PRESETS: [(&str, FrameRate); 6] = [
+    ("24", FrameRate::FPS_24),
+    ("25", FrameRate::FPS_25),
+    ("29.97 DF", FrameRate::FPS_29_97_DF),
+    ("29.97", FrameRate::FPS_29_97),
+    ("30", FrameRate::FPS_30),
+    ("60", FrameRate::FPS_60),
+];
+
+pub(crate) struct KeyDrag {
+    lane_x: f32,
+    origin: Time,
+    /// The dragged keys with their times at the start of the drag and now.
+    keys: Vec<(KeyRef, Time, Time)>,
+}
+
+/// Dragging an edge of a lasting key, see [`Track::key_end`].
+pub(crate) struct KeyTrim {
+    key: KeyRef,
+    edge: Edge,
+    lane_x: f32,
+    origin: Time,
+    /// Start and end of the key when the drag started and now.
+    range: (Time, Time),
+    current: (Time, Time),
+    before: Option<KeyState>,
+}
+
+#[derive(Clone, Copy)]
+enum Edge {
+    Start,
+    End,
+}
+
+pub(crate) struct BoxSelect {
+    start: Point,
+    /// Selection from before the drag, kept when extending with Ctrl/Shift.
+    base: Vec<KeyRef>,
+}
+
+/// Spacing of labeled and unlabeled ruler ticks.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct RulerScale {
+    major: TickStep,
+    minor: TickStep,
+}
+
+impl Default for RulerScale {
+    fn default() -> Self {
+        Self {
+            major: TickStep::Ticks(Time::TICKS_PER_SECOND),
+            minor: TickStep::Ticks(Time::TICKS_PER_SECOND / 10),
+        }
+    }
+}
+
+/// Distance between ruler ticks, frames are counted separately as their length in ticks
+/// is not always whole.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum TickStep {
+    Frames(i64),
+    Ticks(i64),
+}
+
+impl TickStep {
+    /// Index of the last tick at or before `time`.
+    fn index_before(self, time: Time, rate: FrameRate) -> i64 {
+        match self {
+            Self::Frames(n) => time.frame(rate).div_euclid(n),
+            Self::Ticks(n) => time.ticks().div_euclid(n),
+        }
+    }
+
+    fn time(self, index: i64, rate: FrameRate) -> Time {
+        match self {
+            Self::Frames(n) => Time::from_frames(index * n, rate),
+            Self::Ticks(n) => Time::from_ticks(index * n),
+        }
+    }
+
+    fn seconds(self, rate: FrameRate) -> f64 {
+        (self.time(1, rate) - self.time(0, rate)).as_seconds()
+    }
+
+    /// Digits after the second needed to tell labels apart.
+    fn decimals(self, rate: FrameRate) -> usize {
+        let ticks = (self.time(1, rate) - self.time(0, rate)).ticks();
+        (0..3)
+            .find(|digits| ticks % (Time::TICKS_PER_SECOND / 10i64.pow(*digits)) == 0)
+            .unwrap_or(3) as usize
+    }
+}
+
+/// Text being typed into the time cell.
+pub(crate) struct TimeEdit {
+    text: String,
+    error: Option<ParseTimeError>,
+    focus: bool,
+}
+
+impl Timeline {
+    /// Draws the timeline window, call [`Timeline::tick`] to advance playback.
+    pub fn draw(&mut self, ui: &imgui::Ui) -> Response {
+        let before = self.observe();
+        let color_frame_bg = ui.style_color(StyleColor::FrameBg);
+        let color_border = ui.style_color(StyleColor::Border);
+
+        let window_padding = ui.push_style_var(StyleVar::WindowPadding([0.0, 0.0]));
+        let frame_border_size = ui.push_style_var(StyleVar::FrameBorderSize(0.0));
+
+        if let Some(_token) = ui
+            .window(&self.window_ident)
+            .scroll_bar(false)
+            .scrollable(false)
+            .size([200.0, 200.0], imgui::Condition::FirstUseEver)
+            .size_constraints([200.0, 200.0], [f32::INFINITY; 2])
+            .begin()
+        {
+            let wdl = ui.get_window_draw_list();
+            let focused =
+                ui.is_window_focused_with_flags(imgui::WindowFocusedFlags::ROOT_AND_CHILD_WINDOWS);
+
+            let height = ui.text_line_height_with_spacing();
+            let item_spacing = ui.push_style_var(StyleVar::ItemSpacing([4.0, 0.0]));
+
+            self.draw_toolbar(ui, height, &wdl, color_frame_bg, color_border);
+
+            self.draw_editor(ui, height, &wdl, focused);
+
+            self.draw_footer(ui, wdl, color_border);
+
+            item_spacing.pop();
+        }
+        frame_border_size.pop();
+        window_padding.pop();
+
+        self.finish_response(before)
+    }
+
+    fn draw_editor(
+        &mut self,
+        ui: &imgui::Ui,
+        height: f32,
+        wdl: &imgui::DrawListMut<'_>,
+        focused: bool,
+    ) {
+        const ITEM_SPACING: f32 = 2.0;
+        self.snap_guide = None;
+        if let Some(_child) = ui
+            .child_window("editor")
+            .scroll_bar(false)
+            .scrollable(false)
+            .size([0.0, -(height + ITEM_SPACING)])
+            .begin()
+        {
+            let top_left: Point = ui.window_pos().into();
+            let region_max: Point = ui.content_region_max().into();
+            debug! {
+                        wdl.add_rect(
+                            (top_left + Point::new(1.0, 1.0)).array(),
+                            (top_left + region_max - Point::new(1.0, 1.0)).array(),
+                            [0.0, 1.0, 0.0],
+                        )
+                        .thickness(1.0)
+                        .build();
+            }
+            // ui.text("Editor")
+
+            let cell_padding = ui.push_style_var(StyleVar::CellPadding([0.0; 2]));
+            if let Some(_table) = ui.begin_table_with_flags(
+                "track_table",
+                2,
+                imgui::TableFlags::PRECISE_WIDTHS
+                    | imgui::TableFlags::RESIZABLE
+                    | imgui::TableFlags::BORDERS_H
+                    | imgui::TableFlags::BORDERS_V
+                    | imgui::TableFlags::SCROLL_Y,
+            ) {
+                if focused
+                    && self.takes_shortcuts(ui)
+                    && ui.is_key_index_pressed_no_repeat(ImGuiKey_Space)
+                {
+                    self.toggle_playing();
+                }
+                if focused && self.takes_shortcuts(ui) {
+                    if ui.io().key_ctrl {
+                        // Not while dragging, the drag's undo step is still open.
+                        if self.key_drag.is_none()
+                            && self.key_trim.is_none()
+                            && !self.graph.is_dragging()
+                            && ui.is_key_index_pressed(ImGuiKey_Z)
+                        {
+                            if ui.io().key_shift {
+                                self.redo();
+                            } else {
+                                self.undo();
+                            }
+                        }
+                    } else {
+                        if ui.is_key_index_pressed_no_repeat(ImGuiKey_I) {
+                            self.set_in_point(Some(self.display_time()));
+                        }
+                        if ui.is_key_index_pressed_no_repeat(ImGuiKey_O) {
+                            self.set_out_point(Some(self.display_time()));
+                        }
+                        if ui.is_key_index_pressed_no_repeat(ImGuiKey_M) {
+                            let name = format!("M{}", self.markers.len() + 1);
+                            self.add_marker(Marker::new(self.display_time(), name));
+                        }
+                        if ui.is_key_index_pressed_no_repeat(ImGuiKey_S) {
+                            for (key, second) in self.split_at_playhead() {
+                                self.emit(TimelineEvent::KeyChanged(key));
+                                self.emit(TimelineEvent::KeyAdded(second));
+                            }
+                        }
+                        self.handle_shuttle_keys(ui);
+                    }
+                }
+
+                let second_width = SECOND_WIDTH * self.time_scale;
+
+                ui.table_setup_scroll_freeze(2, 1);
+                ui.table_setup_column("##0");
+                ui.table_setup_column("##1");
+
+                const FIRST_HEIGHT: f32 = 35.0;
+                ui.table_next_row();
+                ui.table_set_column_index(0);
+                self.draw_time_cell(ui, FIRST_HEIGHT);
+
+                wdl.add_line(
+                    [top_left.x, top_left.y + FIRST_HEIGHT],
+                    [top_left.x + region_max.x, top_left.y + FIRST_HEIGHT],
+                    [7.0, 7.0, 7.0],
+                )
+                .thickness(1.5)
+                .build();
+
+                ui.table_set_column_index(1);
+
+                if let Some(_t) = ui
+                    .child_window("timeline")
+                    .scroll_bar(false)
+                    .scrollable(false)
+                    .size([0.0, FIRST_HEIGHT])
+                    .begin()
+                {
+                    let top_left: Point = ui.window_pos().into();
+                    let outer_region_max = region_max;
+                    let region_max: Point = ui.content_region_max().into();
+                    self.lane_x = top_left.x;
+                    self.lane_width = region_max.x;
+
+                    //Input
+                    self.handle_scrub(ui, top_left.x, second_width);
+                    if ui.is_window_hovered() && ui.is_mouse_clicked(MouseButton::Right) {
+                        self.ruler_menu_time = self.snap_time(time_from_offset(
+                            self.left_time,
+                            ui.io().mouse_pos[0] - top_left.x,
+                            second_width,
+                        ));
+                        ui.open_popup("ruler_menu");
+                    }
+                    self.draw_ruler_menu(ui);
+                    debug! {
+                                wdl.add_rect(
+                                    (top_left + Point::new(1.0, 1.0)).array(),
+                                    (top_left + region_max - Point::new(1.0, 1.0)).array(),
+                                    [1.0, 1.0, 0.0],
+                                )
+                                .thickness(1.0)
+                                .build();
+                    }
+
+                    let width = region_max.x;
+                    ui.set_window_font_scale(0.9); //TODO: Replace with smaller font
+                    self.ruler_scale =
+                        self.choose_ruler_scale(second_width, |text| ui.calc_text_size(text)[0]);
+                    wdl.with_clip_rect(top_left.array(), (top_left + region_max).array(), || {
+                        if self.has_playback_range() {
+                            let (start, end) = self.playback_range();
+                            let start_x = top_left.x
+                                + (start - self.left_time).as_seconds() as f32 * second_width;
+                            let end_x = top_left.x
+                                + (end - self.left_time).as_seconds() as f32 * second_width;
+                            wdl.add_rect(
+                                [start_x, top_left.y],
+                                [end_x, top_left.y + region_max.y],
+                                [0.3, 0.5, 0.8, 0.3],
+                            )
+                            .filled(true)
+                            .build();
+                            for x in [start_x, end_x] {
+                                wdl.add_line(
+                                    [x, top_left.y],
+                                    [x, top_left.y + region_max.y],
+                                    [0.4, 0.6, 1.0],
+                                )
+                                .thickness(2.0)
+                                .build();
+                            }
+                        }
+                        let rate = self.frame_rate;
+                        let scale = self.ruler_scale;
+                        let tick_x = |time: Time| {
+                            top_left.x + (time - self.left_time).as_seconds() as f32 * second_width
+                        };
+
+                        let mut index = scale.minor.index_before(self.left_time, rate);
+                        loop {
+                            let x = tick_x(scale.minor.time(index, rate));
+                            if x > top_left.x + width {
+                                break;
+                            }
+                            wdl.add_line(
+                                [x, top_left.y + region_max.y],
+                                [x, top_left.y + region_max.y * 0.666],
+                                [0.5; 3],
+                            )
+                            .thickness(1.0)
+                            .build();
+                            index += 1;
+                        }
+
+                        let decimals = scale.major.decimals(rate);
+                        let mut index = scale.major.index_before(self.left_time, rate);
+                        loop {
+                            let time = scale.major.time(index, rate);
+                            let x = tick_x(time);
+                            if x > top_left.x + width {
+                                break;
+                            }
+                            wdl.add_line(
+                                [x, top_left.y + region_max.y],
+                                [x, top_left.y + region_max.y * 0.333],
+                                [1.0; 3],
+                            )
+                            .thickness(1.0)
+                            .build();
+                            wdl.add_text(
+                                [x + 5.0, top_left.y + region_max.y * 0.1],
+                                [1.0; 3],
+                                format!("{:.*}", decimals, time),
+                            );
+                            index += 1;
+                        }
+                        ui.set_window_font_scale(1.0);
+
+                        for marker in &self.markers {
+                            let x = top_left.x
+                                + (marker.time - self.left_time).as_seconds() as f32 * second_width;
+                            wdl.add_polyline(
+                                vec![
+                                    [x - 4.0, top_left.y],
+                                    [x + 4.0, top_left.y],
+                                    [x, top_left.y + 6.0],
+                                ],
+                                [0.95, 0.8, 0.2],
+                            )
+                            .filled(true)
+                            .build();
+                            wdl.add_line(
+                                [x, top_left.y],
+                                [x, top_left.y + region_max.y],
+                                [0.95, 0.8, 0.2, 0.6],
+                            )
+                            .build();
+                        }
+                    });
+                    if ui.is_window_hovered() {
+                        let mouse_x = ui.io().mouse_pos[0];
+                        let hovered = self.markers.iter().find(|m| {
+                            let x = top_left.x
+                                + (m.time - self.left_time).as_seconds() as f32 * second_width;
+                            (x - mouse_x).abs() <= KEY_HIT_RADIUS
+                        });
+                        if let Some(marker) = hovered {
+                            ui.tooltip_text(&marker.name);
+                        }
+                    }
+
+                    self.draw_track_head(top_left, second_width, wdl, region_max, outer_region_max);
+                }
+
+                if self.editor_mode == EditorMode::Graph {
+                    ui.table_next_row();
+                    ui.table_set_column_index(0);
+                    let height = ui.content_region_avail()[1].max(MIN_GRAPH_HEIGHT);
+                    self.draw_graph_track_list(ui, height);
+                    ui.table_set_column_index(1);
+                    self.draw_graph(ui, wdl, second_width, height, focused);
+                    if focused && !self.graph.is_dragging() {
+                        self.handle_edit_keys(ui, None);
+                    }
+                    self.handle_navigation(
+                        ui,
+                        ui.is_window_hovered_with_flags(imgui::WindowHoveredFlags::CHILD_WINDOWS),
+                    );
+                } else {
+                    let body_min: Point = ui.window_pos().into();
+                    let body_max = body_min + Point::from(ui.window_size());
+                    let body_clip = (Point::new(body_min.x, body_min.y + FIRST_HEIGHT), body_max);
+
+                    let layout = LaneLayout {
+                        clip: body_clip,
+                        left_time: self.left_time,
+                        pixels_per_second: second_width,
+                        playhead: self.display_time(),
+                    };
+
+                    let mut lanes = Vec::new();
+                    let mut selected = Vec::new();
+                    for entry in &mut self.tracks {
+                        ui.table_next_row();
+                        ui.table_set_column_index(0);
+                        let _id = ui.push_id_usize(entry.id.0 as usize);
+                        selected.clear();
+                        selected.extend(
+                            self.selection
+                                .iter()
+                                .filter(|r| r.track == entry.id)
+                                .map(|r| r.key),
+                        );
+                        if let Some(lane) =
+                            draw_track(&mut entry.track, ui, wdl, &layout, &selected)
+                        {
+                            lanes.push((entry.id, lane));
+                        }
+                    }
+
+                    if focused {
+                        self.handle_key_input(ui, &lanes, second_width);
+                    }
+                    self.handle_track_drag(ui, wdl, &lanes, body_clip);
+                    self.handle_navigation(
+                        ui,
+                        ui.is_window_hovered_with_flags(imgui::WindowHoveredFlags::CHILD_WINDOWS),
+                    );
+                    if let Some(time) = self.snap_guide {
+                        let x = self.lane_x
+                            + (time - self.left_time).as_seconds() as f32 * second_width;
+                        let clip = (Point::new(self.lane_x, body_min.y), body_max);
+                        wdl.with_clip_rect(clip.0.array(), clip.1.array(), || {
+                            wdl.add_line([x, clip.0.y], [x, clip.1.y], [0.3, 0.9, 1.0, 0.8])
+                                .build();
+                        });
+                    }
+                    if let Some(select) = &self.box_select {
+                        let (min, max) = select.rect(ui.io().mouse_pos.into());
+                        wdl.with_clip_rect(body_clip.0.array(), body_clip.1.array(), || {
+                            wdl.add_rect(min.array(), max.array(), [0.86, 0.554, 0.33, 0.15])
+                                .filled(true)
+                                .build();
+                            wdl.add_rect(min.array(), max.array(), [0.86, 0.554, 0.33, 0.8])
+                                .build();
+                        });
+                    }
+                }
+            }
+
+            cell_padding.pop();
+        }
+    }
+
+    fn handle_key_input(&mut self, ui: &imgui::Ui, lanes: &[(TrackId, Lane)], second_width: f32) {
+        let io = ui.io();
+        let mouse: Point = io.mouse_pos.into();
+        let hovered = lanes
+            .iter()
+            .find_map(|(track, lane)| lane.hover.as_ref().map(|hover| (*track, lane, hover)));
+
+        if let Some((track_id, lane, hover)) = hovered {
+            if ui.is_mouse_double_clicked(MouseButton::Left) && hover.key.is_none() {
+                self.box_select = None;
+                let time = self.snap_time(hover.time);
+                if let Some(key) = self.add_key(track_id, time) {
+                    self.selection = vec![key];
+                    self.emit(TimelineEvent::KeyAdded(key));
+                }
+            } else if ui.is_mouse_clicked(MouseButton::Left) {
+                if let Some((key, edge)) = hover.edge {
+                    let key = KeyRef::new(track_id, key);
+                    self.selection = vec![key];
+                    self.begin_key_trim(key, edge, lane.min.x, hover.time);
+                    return;
+                }
+                match hover.key {
+                    Some(key) => {
+                        let key = KeyRef::new(track_id, key);
+                        self.click_key(key, io.key_ctrl, io.key_shift);
+                        if self.is_key_selected(key) {
+                            self.begin_key_drag(lane.min.x, hover.time);
+                        }
+                    }
+                    None => {
+                        if !io.key_ctrl && !io.key_shift {
+                            self.selection.clear();
+                        }
+                        self.box_select = Some(BoxSelect {
+                            start: mouse,
+                            base: self.selection.clone(),
+                        });
+                    }
+                }
+            }
+        }
+
+        if let Some(mut drag) = self.key_drag.take() {
+            if ui.is_mouse_down(MouseButton::Left) {
+                let time = time_from_offset(self.left_time, mouse.x - drag.lane_x, second_width);
+                let delta = time - drag.origin;
+                // The snap closest to any dragged key moves them all, keeping their spacing.
+                let snap = if io.key_shift {
+                    None
+                } else {
+                    let mut times = Vec::new();
+                    for (key, start, current) in &drag.keys {
+                        times.push(*start + delta);
+                        // Lasting keys also snap by their end.
+                        if let Some(end) = self.track(key.track).and_then(|t| t.key_end(key.key)) {
+                            times.push(end - *current + *start + delta);
+                        }
+                    }
+                    let keys = drag.keys.iter().map(|k| k.0).collect::<Vec<_>>();
+                    self.find_snap(&times, &keys, true, second_width)
+                };
+                self.snap_guide = snap.map(|(_, target)| target);
+                let targets = drag
+                    .keys
+                    .iter()
+                    .map(|(_, start, _)| match snap {
+                        Some((offset, _)) => *start + delta + offset,
+                        None if io.key_shift => *start + delta,
+                        None => self.snap_time(*start + delta),
+                    })
+                    .collect::<Vec<_>>();
+                // A key blocked by another dragged key, like a clip pushed against the next
+                // one, moves once the other key made room.
+                let mut pending = (0..targets.len())
+                    .filter(|i| targets[*i] != drag.keys[*i].2)
+                    .collect::<Vec<_>>();
+                while !pending.is_empty() {
+                    let count = pending.len();
+                    pending.retain(|i| {
+                        let (key, _, current) = &mut drag.keys[*i];
+                        if !self.move_key(*key, targets[*i]) {
+                            return true;
+                        }
+                        self.emit(TimelineEvent::KeyMoved {
+                            key: *key,
+                            from: *current,
+                            to: targets[*i],
+                        });
+                        *current = targets[*i];
+                        false
+                    });
+                    if pending.len() == count {
+                        break;
+                    }
+                }
+                self.key_drag = Some(drag);
+            } else {
+                self.end_group();
+            }
+        }
+
+        if let Some(trim) = self.key_trim.take() {
+            self.update_key_trim(ui, trim, second_width);
+        }
+
+        if let Some(select) = &self.box_select {
+            let (min, max) = select.rect(mouse);
+            let mut selection = select.base.clone();
+            for (track_id, lane) in lanes {
+                if lane.max.y < min.y || lane.min.y > max.y {
+                    continue;
+                }
+                let Some(track) = self.track(*track_id) else {
+                    continue;
+                };
+                for (key, time) in track.key_times() {
+                    let x = lane.min.x + (time - self.left_time).as_seconds() as f32 * second_width;
+                    let key = KeyRef::new(*track_id, key);
+                    if x >= min.x && x <= max.x && !selection.contains(&key) {
+                        selection.push(key);
+                    }
+                }
+            }
+            self.selection = selection;
+
+            if !ui.is_mouse_down(MouseButton::Left) {
+                self.box_select = None;
+            }
+        }
+
+        self.handle_edit_keys(ui, hovered.map(|(track, _, _)| track));
+    }
+
+    /// Ctrl toggles the clicked key, Shift adds it, otherwise it becomes the selection unless
+    /// it is already selected, so a selection can be dragged by any of its keys.
+    pub(crate) fn click_key(&mut self, key: KeyRef, ctrl: bool, shift: bool) {
+        if ctrl {
+            if self.is_key_selected(key) {
+                self.deselect_key(key);
+            } else {
+                self.select_key(key);
+            }
+        } else if shift {
+            self.select_key(key);
+        } else if !self.is_key_selected(key) {
+            self.selection = vec![key];
+        }
+    }
+
+    /// Whether keys are shortcuts rather than text typed into the time field or another input.
+    pub(crate) fn takes_shortcuts(&self, ui: &imgui::Ui) -> bool {
+        self.time_edit.is_none() && !ui.io().want_text_input
+    }
+
+    /// Clipboard shortcuts and Delete, shared by both editors.
+    fn handle_edit_keys(&mut self, ui: &imgui::Ui, hovered_track: Option<TrackId>) {
+        if !self.takes_shortcuts(ui) {
+            return;
+        }
+        if ui.io().key_ctrl && self.key_drag.is_none() {
+            self.handle_clipboard_keys(ui, hovered_track);
+        }
+
+        if ui.is_key_index_pressed_no_repeat(ImGuiKey_Delete) {
+            if self.key_drag.take().is_some() {
+                self.end_group();
+            }
+            self.begin_group();
+            for key in self.selection.clone() {
+                if self.delete_key(key) {
+                    self.emit(TimelineEvent::KeyDeleted(key));
+                }
+            }
+            self.end_group();
+            self.selection.clear();
+        }
+    }
+
+    /// Dragging a track head onto another row moves the track there.
+    fn handle_track_drag(
+        &mut self,
+        ui: &imgui::Ui,
+        wdl: &imgui::DrawListMut<'_>,
+        lanes: &[(TrackId, Lane)],
+        clip: (Point, Point),
+    ) {
+        if self.track_drag.is_none() && ui.is_mouse_clicked(MouseButton::Left) {
+            self.track_drag = lanes
+                .iter()
+                .find(|(_, lane)| lane.head_hovered)
+                .map(|(track, _)| *track);
+        }
+        let Some(track) = self.track_drag else {
+            return;
+        };
+        let (Some(from), Some((target, lane))) = (
+            self.track_index(track),
+            // Rows dragged past the visible ones go to the last visible row.
+            lanes
+                .iter()
+                .find(|(_, lane)| ui.io().mouse_pos[1] < lane.max.y)
+                .or(lanes.last()),
+        ) else {
+            self.track_drag = None;
+            return;
+        };
+        let to = self.track_index(*target).unwrap_or(from);
+
+        if ui.is_mouse_down(MouseButton::Left) {
+            if to != from {
+                let y = if to < from { lane.min.y } else { lane.max.y };
+                wdl.with_clip_rect(clip.0.array(), clip.1.array(), || {
+                    wdl.add_line([clip.0.x, y], [clip.1.x, y], [0.86, 0.554, 0.33])
+                        .thickness(2.0)
+                        .build();
+                });
+            }
+        } else {
+            self.track_drag = None;
+            if to != from && self.move_track(track, to) {
+                self.emit(TimelineEvent::TrackMoved { track, from, to });
+            }
+        }
+    }
+
+    /// Ctrl+C/X/V, pastes go at the playhead onto the hovered track, or the track of the
+    /// first selected key.
+    fn handle_clipboard_keys(&mut self, ui: &imgui::Ui, hovered_track: Option<TrackId>) {
+        if ui.is_key_index_pressed_no_repeat(ImGuiKey_C) {
+            if let Some(text) = self.copy_selection() {
+                ui.set_clipboard_text(text);
+            }
+        }
+        if ui.is_key_index_pressed_no_repeat(ImGuiKey_X) {
+            if let Some((text, deleted)) = self.cut_keys() {
+                ui.set_clipboard_text(text);
+                for key in deleted {
+                    self.emit(TimelineEvent::KeyDeleted(key));
+                }
+            }
+        }
+        if ui.is_key_index_pressed_no_repeat(ImGuiKey_V) {
+            let target = hovered_track
+                .or_else(|| self.selection.first().map(|key| key.track))
+                .or_else(|| self.tracks.first().map(|e| e.id));
+            let (Some(text), Some(target)) = (ui.clipboard_text(), target) else {
+                return;
+            };
+            match self.paste(&text, target, self.display_time()) {
+                Ok(keys) => {
+                    for key in keys {
+                        self.emit(TimelineEvent::KeyAdded(key));
+                    }
+                }
+                Err(error) => self.emit(TimelineEvent::PasteRejected(error)),
+            }
+        }
+    }
+
+    fn begin_key_trim(&mut self, key: KeyRef, edge: Edge, lane_x: f32, origin: Time) {
+        let Some(track) = self.track(key.track) else {
+            return;
+        };
+        let start = track.key_times().into_iter().find(|(k, _)| *k == key.key);
+        let (Some((_, start)), Some(end)) = (start, track.key_end(key.key)) else {
+            return;
+        };
+        self.key_trim = Some(KeyTrim {
+            key,
+            edge,
+            lane_x,
+            origin,
+            range: (start, end),
+            current: (start, end),
+            before: track.key_state(key.key),
+        });
+    }
+
+    /// Moves the dragged edge, snapping like dragged keys. Releasing records the trim.
+    fn update_key_trim(&mut self, ui: &imgui::Ui, mut trim: KeyTrim, second_width: f32) {
+        let io = ui.io();
+        ui.set_mouse_cursor(Some(MouseCursor::ResizeEW));
+        if !ui.is_mouse_down(MouseButton::Left) {
+            if trim.current != trim.range {
+                let after = self
+                    .track(trim.key.track)
+                    .and_then(|t| t.key_state(trim.key.key));
+                if let (Some(before), Some(after)) = (trim.before, after) {
+                    self.record_change_key(trim.key, before, after);
+                }
+                self.emit(TimelineEvent::KeyChanged(trim.key));
+            }
+            return;
+        }
+
+        let time = time_from_offset(self.left_time, io.mouse_pos[0] - trim.lane_x, second_width);
+        let (start, end) = trim.range;
+        let edge_time = match trim.edge {
+            Edge::Start => start,
+            Edge::End => end,
+        } + (time - trim.origin);
+        let edge_time = if io.key_shift {
+            edge_time
+        } else if let Some((_, target)) =
+            self.find_snap(&[edge_time], &[trim.key], true, second_width)
+        {
+            self.snap_guide = Some(target);
+            target
+        } else {
+            self.snap_time(edge_time)
+        };
+        let range = match trim.edge {
+            Edge::Start => (edge_time, end),
+            Edge::End => (start, edge_time),
+        };
+        if range != trim.current
+            && self
+                .track_mut(trim.key.track)
+                .is_some_and(|t| t.set_key_range(trim.key.key, range.0, range.1))
+        {
+            trim.current = range;
+        }
+        self.key_trim = Some(trim);
+    }
+
+    fn begin_key_drag(&mut self, lane_x: f32, origin: Time) {
+        self.prune_selection();
+        let mut keys = Vec::new();
+        for entry in &self.tracks {
+            for (key, time) in entry.track.key_times() {
+                let key = KeyRef::new(entry.id, key);
+                if self.selection.contains(&key) {
+                    keys.push((key, time, time));
+                }
+            }
+        }
+        self.begin_group();
+        self.key_drag = Some(KeyDrag {
+            lane_x,
+            origin,
+            keys,
+        });
+    }
+
+    /// Dragging on the ruler, which includes the playhead handle, scrubs the playhead.
+    fn handle_scrub(&mut self, ui: &imgui::Ui, ruler_x: f32, second_width: f32) {
+        if !self.scrubbing && ui.is_window_hovered() && ui.is_mouse_clicked(MouseButton::Left) {
+            self.scrubbing = true;
+            self.emit(TimelineEvent::ScrubStarted);
+        }
+        if !self.scrubbing {
+            return;
+        }
+
+        if ui.is_mouse_down(MouseButton::Left) {
+            let time =
+                time_from_offset(self.left_time, ui.io().mouse_pos[0] - ruler_x, second_width);
+            self.time = if ui.io().key_shift {
+                time
+            } else if let Some((_, target)) = self.find_snap(&[time], &[], false, second_width) {
+                self.snap_guide = Some(target);
+                target
+            } else {
+                self.snap_time(time)
+            };
+        } else {
+            self.scrubbing = false;
+            self.emit(TimelineEvent::ScrubEnded);
+        }
+    }
+
+    /// The closest of the minor ruler ticks drawn last.
+    pub(crate) fn nearest_ruler_tick(&self, time: Time) -> Time {
+        let step = self.ruler_scale.minor;
+        let rate = self.frame_rate;
+        let before = step.time(step.index_before(time, rate), rate);
+        let after = step.time(step.index_before(time, rate) + 1, rate);
+        if time - before <= after - time {
+            before
+        } else {
+            after
+        }
+    }
+
+    /// Picks the finest major ticks whose labels don't overlap, `text_width` measures the
+    /// labels in the current font. Minor ticks are frames when those are wide enough.
+    fn choose_ruler_scale(
+        &self,
+        second_width: f32,
+        text_width: impl Fn(&str) -> f32,
+    ) -> RulerScale {
+        let rate = self.frame_rate;
+        let frame_ticks = second_width / rate.as_fps() as f32 >= MIN_FRAME_TICK_SPACING;
+        let fine_steps = if frame_ticks {
+            // Whole frames below a second, sub-frame labels would repeat.
+            let frames_per_second = rate.as_fps().ceil() as i64;
+            [1, 2, 5, 10, 15]
+                .into_iter()
+                .filter(|n| *n < frames_per_second)
+                .map(TickStep::Frames)
+                .collect::<Vec<_>>()
+        } else {
+            [1, 2, 5, 10, 20, 50, 100, 200, 500]
+                .into_iter()
+                .map(|ms| TickStep::Ticks(ms * Time::TICKS_PER_SECOND / 1000))
+                .collect()
+        };
+        let coarse_steps = [1, 2, 5, 10, 15, 30, 60, 120, 300, 600, 900, 1800, 3600]
+            .into_iter()
+            .map(|s| TickStep::Ticks(s * Time::TICKS_PER_SECOND));
+
+        let mut steps = fine_steps.into_iter().chain(coarse_steps).peekable();
+        let major = loop {
+            let step = steps.next().expect("steps are not empty");
+            let label = format!("-{:.*}", step.decimals(rate), Time::ZERO);
+            let label_width = text_width(&label) + MIN_LABEL_GAP;
+            if step.seconds(rate) as f32 * second_width >= label_width || steps.peek().is_none() {
+                break step;
+            }
+        };
+
+        let minor = match major {
+            _ if frame_ticks => TickStep::Frames(1),
+            TickStep::Frames(n) => TickStep::Frames(n),
+            TickStep::Ticks(ticks) => {
+                let major_width = major.seconds(rate) as f32 * second_width;
+                let divisions = [10, 5, 2]
+                    .into_iter()
+                    .find(|n| major_width / *n as f32 >= MIN_MINOR_TICK_SPACING)
+                    .unwrap_or(1);
+                TickStep::Ticks(ticks / divisions)
+            }
+        };
+        RulerScale { major, minor }
+    }
+
+    /// J/K/L shuttle, holding K while pressing J or L steps single frames instead.
+    fn handle_shuttle_keys(&mut self, ui: &imgui::Ui) {
+        let holding_k = ui.is_key_index_down(ImGuiKey_K);
+        if ui.is_key_index_pressed_no_repeat(ImGuiKey_K) {
+            self.pause();
+        }
+        if ui.is_key_index_pressed(ImGuiKey_J) {
+            if holding_k {
+                self.step_frames(-1);
+            } else {
+                self.shuttle_backward();
+            }
+        }
+        if ui.is_key_index_pressed(ImGuiKey_L) {
+            if holding_k {
+                self.step_frames(1);
+            } else {
+                self.shuttle_forward();
+            }
+        }
+    }
+
+    /// Context menu of the ruler, edits the playback range at the clicked time.
+    fn draw_ruler_menu(&mut self, ui: &imgui::Ui) {
+        ui.popup("ruler_menu", || {
+            if ui.menu_item_config("Set in point").shortcut("I").build() {
+                self.set_in_point(Some(self.ruler_menu_time));
+            }
+            if ui.menu_item_config("Set out point").shortcut("O").build() {
+                self.set_out_point(Some(self.ruler_menu_time));
+            }
+            if ui
+                .menu_item_config("Clear range")
+                .enabled(self.has_playback_range())
+                .build()
+            {
+                self.clear_playback_range();
+            }
+            ui.separator();
+            for mode in PlaybackMode::ALL {
+                if ui
+                    .menu_item_config(mode.name())
+                    .selected(self.playback_mode == mode)
+                    .build()
+                {
+                    self.playback_mode = mode;
+                }
+            }
+        });
+    }
+
+    /// The top left cell, shows the playhead time and lets it be typed in.
+    fn draw_time_cell(&mut self, ui: &imgui::Ui, height: f32) {
+        let max: Point = ui.content_region_max().into();
+        let y = ui.cursor_pos()[1];
+
+        if let Some(edit) = &mut self.time_edit {
+            ui.set_cursor_pos([4.0, y + (height - ui.frame_height()) / 2.0]);
+            ui.set_next_item_width(max.x - 8.0);
+            if edit.focus {
+                ui.set_keyboard_focus_here();
+                edit.focus = false;
+            }
+            let error_color = edit
+                .error
+                .as_ref()
+                .map(|_| ui.push_style_color(StyleColor::Text, [1.0, 0.35, 0.35, 1.0]));
+            let entered = ui
+                .input_text("##time_edit", &mut edit.text)
+                .enter_returns_true(true)
+                .auto_select_all(true)
+                .build();
+            drop(error_color);
+
+            if entered {
+                match Time::parse(&edit.text, self.time_format, self.frame_rate) {
+                    Ok(time) => {
+                        self.time = self.snap_time(time);
+                        self.time_edit = None;
+                    }
+                    Err(error) => {
+                        // Enter deactivates the input, keep it open to fix the typo.
+                        edit.error = Some(error);
+                        edit.focus = true;
+                    }
+                }
+            } else if ui.is_item_deactivated() {
+                // Escape or clicking elsewhere.
+                self.time_edit = None;
+            } else if let Some(error) = &edit.error {
+                ui.tooltip_text(error.to_string());
+            }
+            return;
+        }
+
+        ui.set_window_font_scale(1.25); //TODO: Replace with bigger font.
+        let time = self
+            .display_time()
+            .format(self.time_format, self.frame_rate);
+        let size: Point = ui.calc_text_size(&time).into();
+        ui.set_cursor_pos([
+            (max.x - size.x) / 2.0,
+            y + (height - ui.text_line_height_with_spacing()) / 2.0,
+        ]);
+        ui.text(&time);
+        ui.set_window_font_scale(1.0);
+
+        if ui.is_item_hovered() {
+            ui.tooltip_text("Click to enter a time, right-click to change the format");
+        }
+        if ui.is_item_clicked() {
+            self.time_edit = Some(TimeEdit {
+                text: time,
+                error: None,
+                focus: true,
+            });
+        }
+        if ui.is_item_clicked_with_button(MouseButton::Right) {
+            ui.open_popup("time_format");
+        }
+        ui.popup("time_format", || {
+            for format in TimeFormat::ALL {
+                if ui
+                    .selectable_config(format.name())
+                    .selected(format == self.time_format)
+                    .build()
+                {
+                    self.time_format = format;
+                }
+            }
+        });
+    }
+
+    fn draw_track_head(
+        &mut self,
+        top_left: cgmath::Vector2<f32>,
+        second_width: f32,
+        wdl: &imgui::DrawListMut<'_>,
+        region_max: cgmath::Vector2<f32>,
+        outer_region_max: cgmath::Vector2<f32>,
+    ) {
+        let track_head_center =
+            top_left.x + (self.display_time() - self.left_time).as_seconds() as f32 * second_width;
+
+        if track_head_center >= top_left.x {
+            wdl.add_polyline(
+                vec![
+                    [track_head_center, top_left.y + region_max.y],
+                    [track_head_center - 5.0, top_left.y + region_max.y * 0.8],
+                    [track_head_center - 5.0, top_left.y + region_max.y * 0.6],
+                    [track_head_center + 5.0, top_left.y + region_max.y * 0.6],
+                    [track_head_center + 5.0, top_left.y + region_max.y * 0.8],
+                ],
+                [1.0, 0.0, 0.0],
+            )
+            .filled(true)
+            .thickness(2.0)
+            .build();
+
+            wdl.add_line(
+                [track_head_center, top_left.y + region_max.y * 0.7],
+                [track_head_center, top_left.y + outer_region_max.y],
+                [1.0, 0.0, 0.0],
+            )
+            .thickness(2.0)
+            .build();
+        }
+    }
+
+    fn draw_toolbar(
+        &mut self,
+        ui: &imgui::Ui,
+        height: f32,
+        wdl: &imgui::DrawListMut<'_>,
+        color_frame_bg: [f32; 4],
+        color_border: [f32; 4],
+    ) {
+        if let Some(_child) = ui
+            .child_window("toolbar")
+            .scroll_bar(false)
+            .scrollable(false)
+            .size([0.0, height])
+            .begin()
+        {
+            let top_left: Point = ui.window_pos().into();
+            let region_max: Point = ui.content_region_max().into();
+            debug! {
+                        wdl.add_rect(
+                            (top_left + Point::new(1.0, 1.0)).array(),
+                            (top_left + region_max - Point::new(1.0, 1.0)).array(),
+                            [1.0, 0.0, 0.0],
+                        )
+                        .thickness(1.0)
+                        .build();
+            };
+
+            //BG
+            wdl.add_rect(
+                top_left.array(),
+                (top_left + region_max).array(),
+                color_frame_bg,
+            )
+            .filled(true)
+            .build();
+            wdl.add_line(
+                [top_left.x, top_left.y + region_max.y],
+                [top_left.x + region_max.x, top_left.y + region_max.y],
+                color_border,
+            )
+            .thickness(1.0)
+            .build();
+
+            ui.set_cursor_pos([2.0, (region_max.y - height) / 2.0 + 2.0]);
+
+            // Buttons end with `same_line`.
+            self.draw_transport(ui);
+            ui.text(" ");
+            ui.same_line();
+
+            //Scale Input
+            ui.text("Scale:");
+            ui.same_line();
+            ui.set_next_item_width(50.0);
+            Drag::new("##scale")
+                .speed(0.05)
+                .range(MIN_TIME_SCALE, MAX_TIME_SCALE)
+                .display_format("%.2f")
+                .build(ui, &mut self.time_scale);
+
+            ui.same_line();
+
+            //SPeed Input
+            ui.text("Speed:");
+            ui.same_line();
+            ui.set_next_item_width(50.0);
+            Drag::new("##speed")
+                .speed(0.01)
+                .range(-MAX_PLAYBACK_SPEED, MAX_PLAYBACK_SPEED)
+                .display_format("%.2f")
+                .build(ui, &mut self.playback_speed);
+
+            ui.same_line();
+
+            //Playback mode
+            ui.set_next_item_width(90.0);
+            if let Some(_combo) = ui.begin_combo("##mode", self.playback_mode.name()) {
+                for mode in PlaybackMode::ALL {
+                    if ui
+                        .selectable_config(mode.name())
+                        .selected(self.playback_mode == mode)
+                        .build()
+                    {
+                        self.playback_mode = mode;
+                    }
+                }
+            }
+
+            ui.same_line();
+
+            //Frame rate
+            ui.text("FPS:");
+            ui.same_line();
+            ui.set_next_item_width(80.0);
+            let preview = FRAME_RATE_PRESETS
+                .iter()
+                .find(|(_, rate)| *rate == self.frame_rate)
+                .map(|(name, _)| name.to_string())
+                .unwrap_or_else(|| format!("{:.3}", self.frame_rate.as_fps()));
+            if let Some(_combo) = ui.begin_combo("##fps", preview) {
+                for (name, rate) in FRAME_RATE_PRESETS {
+                    if ui
+                        .selectable_config(name)
+                        .selected(self.frame_rate == rate)
+                        .build()
+                    {
+                        self.frame_rate = rate;
+                    }
+                }
+                ui.separator();
+                // Any other rate as frames per number of seconds, taken once both are positive.
+                let mut parts = [self.frame_rate.numerator(), self.frame_rate.denominator()]
+                    .map(|part| part.min(i32::MAX as u32) as i32);
+                ui.set_next_item_width(80.0);
+                let mut changed = ui.input_int("Frames", &mut parts[0]).step(0).build();
+                ui.set_next_item_width(80.0);
+                changed |= ui.input_int("Seconds", &mut parts[1]).step(0).build();
+                if changed && parts[0] > 0 && parts[1] > 0 {
+                    self.frame_rate = FrameRate::new(parts[0] as u32, parts[1] as u32);
+                }
+            }
+
+            ui.same_line();
+
+            //Editor mode
+            ui.set_next_item_width(90.0);
+            if let Some(_combo) = ui.begin_combo("##editor", self.editor_mode.name()) {
+                for mode in EditorMode::ALL {
+                    if ui
+                        .selectable_config(mode.name())
+                        .selected(self.editor_mode == mode)
+                        .build()
+                    {
+                        self.set_editor_mode(mode);
+                    }
+                }
+            }
+
+            ui.same_line();
+            if ui.small_button("Snap") {
+                ui.open_popup("snap_settings");
+            }
+            ui.popup("snap_settings", || {
+                ui.text_disabled("Hold Shift while dragging to disable");
+                ui.checkbox("Frames", &mut self.snap.frames);
+                ui.checkbox("Ruler ticks", &mut self.snap.ruler_ticks);
+                ui.checkbox("Keys", &mut self.snap.keys);
+                ui.checkbox("Markers", &mut self.snap.markers);
+                ui.checkbox("Playhead", &mut self.snap.playhead);
+            });
+        };
+    }
+
+    /// Play, stop, frame step and jump buttons, each also a method on [`Timeline`].
+    fn draw_transport(&mut self, ui: &imgui::Ui) {
+        let button = |label: &str, tooltip: &str| {
+            let clicked = ui.small_button(label);
+            if ui.is_item_hovered() {
+                ui.tooltip_text(tooltip);
+            }
+            ui.same_line();
+            clicked
+        };
+
+        if button("|<", "Jump to range start") {
+            self.jump_to_range_start();
+        }
+        if button("[<", "Jump to previous key") {
+            self.jump_to_previous_key();
+        }
+        if button("<|##step_back", "Step one frame back") {
+            self.step_frames(-1);
+        }
+        let reverse_playing = self.playing && self.is_reversed();
+        if button(
+            if reverse_playing { "||##reverse" } else { "<" },
+            "Play reverse (J)",
+        ) {
+            if reverse_playing {
+                self.pause();
+            } else {
+                self.play_reverse();
+            }
+        }
+        if button("[]", "Stop") {
+            self.stop();
+        }
+        let forward_playing = self.playing && self.playback_speed > 0.0;
+        if button(
+            if forward_playing { "||##play" } else { ">" },
+            "Play (Space, L)",
+        ) {
+            if forward_playing {
+                self.pause();
+            } else {
+                self.play();
+            }
+        }
+        if button("|>##step_forward", "Step one frame forward") {
+            self.step_frames(1);
+        }
+        if button(">]", "Jump to next key") {
+            self.jump_to_next_key();
+        }
+        if button(">|", "Jump to range end") {
+            self.jump_to_range_end();
+        }
+    }
+
+    fn draw_footer(&mut self, ui: &imgui::Ui, wdl: imgui::DrawListMut<'_>, color_border: [f32; 4]) {
+        if let Some(_child) = ui
+            .child_window("footer")
+            .scroll_bar(false)
+            .scrollable(false)
+            .size([0.0, 0.0])
+            .begin()
+        {
+            let top_left: Point = ui.window_pos().into();
+            let region_max: Point = ui.content_region_max().into();
+            debug! {
+                        wdl.add_rect(
+                            (top_left + Point::new(1.0, 1.0)).array(),
+                            (top_left + region_max - Point::new(1.0, 1.0)).array(),
+                            [0.0, 0.0, 1.0],
+                        )
+                        .thickness(1.0)
+                        .build();
+            }
+
+            wdl.add_line(
+                [top_left.x, top_left.y],
+                [top_left.x + region_max.x, top_left.y],
+                color_border,
+            )
+            .thickness(1.0)
+            .build();
+
+            self.draw_scrollbar(ui, &wdl, top_left, region_max);
+        }
+    }
+
+    fn draw_scrollbar(
+        &mut self,
+        ui: &imgui::Ui,
+        wdl: &imgui::DrawListMut<'_>,
+        top_left: Point,
+        region_max: Point,
+    ) {
+        let second_width = SECOND_WIDTH * self.time_scale;
+        let visible = Time::from_seconds((self.lane_width / second_width) as f64);
+        let start = self.left_time.min(Time::ZERO);
+        let total = (self.content_end().max(self.left_time + visible) - start).as_seconds() as f32;
+
+        let bar_min = Point::new(self.lane_x.max(top_left.x), top_left.y + 3.0);
+        let bar_max = Point::new(
+            top_left.x + region_max.x - 2.0,
+            top_left.y + region_max.y - 2.0,
+        );
+        let bar_width = bar_max.x - bar_min.x;
+        if bar_width <= 0.0 || total <= 0.0 {
+            return;
+        }
+
+        let thumb_min =
+            bar_min.x + (self.left_time - start).as_seconds() as f32 / total * bar_width;
+        let thumb_max =
+            (thumb_min + visible.as_seconds() as f32 / total * bar_width).max(thumb_min + 10.0);
+
+        ui.set_cursor_screen_pos(bar_min.array());
+        ui.invisible_button("##scrollbar", (bar_max - bar_min).array());
+        let mouse_x = ui.io().mouse_pos[0];
+        if ui.is_item_activated() && (mouse_x < thumb_min || mouse_x > thumb_max) {
+            // Jump so the thumb is centered on the click.
+            let center = Time::from_seconds(((mouse_x - bar_min.x) / bar_width * total) as f64);
+            self.left_time = start + center - visible / 2;
+        } else if ui.is_item_active() {
+            let delta = ui.io().mouse_delta[0];
+            self.left_time += Time::from_seconds((delta / bar_width * total) as f64);
+        }
+
+        let thumb_color = if ui.is_item_active() {
+            ui.style_color(StyleColor::ScrollbarGrabActive)
+        } else if ui.is_item_hovered() {
+            ui.style_color(StyleColor::ScrollbarGrabHovered)
+        } else {
+            ui.style_color(StyleColor::ScrollbarGrab)
+        };
+        let rounding = (bar_max.y - bar_min.y) / 2.0;
+        wdl.add_rect(
+            bar_min.array(),
+            bar_max.array(),
+            ui.style_color(StyleColor::ScrollbarBg),
+        )
+        .filled(true)
+        .rounding(rounding)
+        .build();
+        wdl.add_rect(
+            [thumb_min, bar_min.y],
+            [thumb_max.min(bar_max.x), bar_max.y],
+            thumb_color,
+        )
+        .filled(true)
+        .rounding(rounding)
+        .build();
+    }
+
+    /// Ctrl+wheel zooms around the mouse, Shift+wheel, the horizontal wheel and
+    /// middle-dragging pan. Called with the track table as the current window.
+    fn handle_navigation(&mut self, ui: &imgui::Ui, hovered: bool) {
+        let io = ui.io();
+        let second_width = SECOND_WIDTH * self.time_scale;
+
+        if let Some(scroll_y) = self.pending_scroll_y.take() {
+            ui.set_scroll_y(scroll_y);
+            self.track_scroll_y = scroll_y;
+        } else if hovered && io.key_ctrl && io.mouse_wheel != 0.0 {
+            let offset = io.mouse_pos[0] - self.lane_x;
+            let anchor = time_from_offset(self.left_time, offset, second_width);
+            self.time_scale = (self.time_scale * ZOOM_STEP.powf(io.mouse_wheel))
+                .clamp(MIN_TIME_SCALE, MAX_TIME_SCALE);
+            self.left_time = time_from_offset(anchor, -offset, SECOND_WIDTH * self.time_scale);
+            // The table already scrolled vertically on this wheel event, undo that.
+            ui.set_scroll_y(self.track_scroll_y);
+        } else {
+            self.track_scroll_y = ui.scroll_y();
+        }
+
+        let mut pan = io.mouse_wheel_h;
+        if io.key_shift && !io.key_ctrl {
+            pan += io.mouse_wheel;
+        }
+        if hovered && pan != 0.0 {
+            self.left_time = time_from_offset(self.left_time, -pan * PAN_STEP, second_width);
+        }
+
+        if hovered && ui.is_mouse_clicked(MouseButton::Middle) {
+            self.panning = true;
+        }
+        if self.panning {
+            if ui.is_mouse_down(MouseButton::Middle) {
+                self.left_time = time_from_offset(self.left_time, -io.mouse_delta[0], second_width);
+            } else {
+                self.panning = false;
+            }
+        }
+    }
+}
+
+struct LaneLayout {
+    clip: (Point, Point),
+    left_time: Time,
+    pixels_per_second: f32,
+    playhead: Time,
+}
+
+struct Lane {
+    min: Point,
+    max: Point,
+    hover: Option<LaneHover>,
+    head_hovered: bool,
+}
+
+struct LaneHover {
+    time: Time,
+    key: Option<KeyId>,
+    /// Edge of a lasting key close enough to be dragged.
+    edge: Option<(KeyId, Edge)>,
+}
+
+impl BoxSelect {
+    fn rect(&self, mouse: Point) -> (Point, Point) {
+        (
+            Point::new(self.start.x.min(mouse.x), self.start.y.min(mouse.y)),
+            Point::new(self.start.x.max(mouse.x), self.start.y.max(mouse.y)),
+        )
+    }
+}
+
+/// Draws `name` centered in a track head of `height`, the head of the built-in tracks.
+pub(crate) fn draw_track_name(ui: &imgui::Ui, name: &str, height: f32) {
+    let max: Point = ui.content_region_max().into();
+    let size: Point = ui.calc_text_size(name).into();
+    ui.set_cursor_pos([
+        (max.x - size.x) / 2.0,
+        ui.cursor_pos()[1] + (height - ui.text_line_height_with_spacing()) / 2.0,
+    ]);
+    ui.text(name);
+}
+
+fn draw_track(
+    track: &mut Box<dyn Track>,
+    ui: &imgui::Ui,
+    wdl: &imgui::DrawListMut<'_>,
+    layout: &LaneLayout,
+    selected_keys: &[KeyId],
+) -> Option<Lane> {
+    let config = track.head_config();
+    let mut head_hovered = false;
+    if let Some(_c) = ui
+        .child_window(&config.0)
+        .scroll_bar(false)
+        .scrollable(false)
+        .size([0.0, config.1])
+        .begin()
+    {
+        track.draw_head(ui);
+        head_hovered = ui.is_window_hovered() && !ui.is_any_item_hovered();
+    }
+
+    ui.table_set_column_index(1);
+    let _c = ui
+        .child_window(format!("{}_lane", config.0))
+        .scroll_bar(false)
+        .scrollable(false)
+        .size([0.0, config.1])
+        .begin()?;
+
+    let lane_min: Point = ui.window_pos().into();
+    let lane_max = lane_min + Point::from(ui.window_size());
+    let view = TrackView {
+        draw_list: wdl,
+        lane_min,
+        lane_max,
+        left_time: layout.left_time,
+        right_time: time_from_offset(
+            layout.left_time,
+            lane_max.x - lane_min.x,
+            layout.pixels_per_second,
+        ),
+        pixels_per_second: layout.pixels_per_second,
+        playhead: layout.playhead,
+        selected_keys,
+    };
+
+    let clip_min = Point::new(
+        lane_min.x.max(layout.clip.0.x),
+        lane_min.y.max(layout.clip.0.y),
+    );
+    let clip_max = Point::new(
+        lane_max.x.min(layout.clip.1.x),
+        lane_max.y.min(layout.clip.1.y),
+    );
+    if clip_min.x >= clip_max.x || clip_min.y >= clip_max.y {
+        return None;
+    }
+    wdl.with_clip_rect(clip_min.array(), clip_max.array(), || {
+        track.draw_lane(ui, &view)
+    });
+
+    let hover = ui.is_window_hovered().then(|| {
+        let mouse_x = ui.io().mouse_pos[0];
+        let keys = track.key_times();
+        let edge = keys.iter().find_map(|&(key, start)| {
+            let (start_x, end_x) = (view.time_to_x(start), view.time_to_x(track.key_end(key)?));
+            // Narrow bars are only moved.
+            if end_x - start_x < 3.0 * EDGE_HIT_WIDTH {
+                None
+            } else if (mouse_x - start_x).abs() <= EDGE_HIT_WIDTH {
+                Some((key, Edge::Start))
+            } else if (mouse_x - end_x).abs() <= EDGE_HIT_WIDTH {
+                Some((key, Edge::End))
+            } else {
+                None
+            }
+        });
+        if edge.is_some() {
+            ui.set_mouse_cursor(Some(MouseCursor::ResizeEW));
+        }
+        let key = keys
+            .into_iter()
+            .map(|(key, time)| {
+                let x = view.time_to_x(time);
+                let distance = match track.key_end(key).map(|end| view.time_to_x(end)) {
+                    Some(end_x) if mouse_x >= x && mouse_x <= end_x => 0.0,
+                    _ => (x - mouse_x).abs(),
+                };
+                (key, distance)
+            })
+            .filter(|(_, distance)| *distance <= KEY_HIT_RADIUS)
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(key, _)| key);
+        LaneHover {
+            time: view.x_to_time(mouse_x),
+            key,
+            edge,
+        }
+    });
+    Some(Lane {
+        min: lane_min,
+        max: lane_max,
+        hover,
+        head_hovered,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{RulerScale, TickStep};
+    use crate::{EventTrack, FrameRate, KeyRef, Time, Timeline};
+
+    #[test]
+    fn click_modifiers_change_the_selection() {
+        let mut timeline = Timeline::new("test");
+        let mut events = EventTrack::new("events");
+        let ids = [0, 1, 2].map(|i| events.insert_event(Time::from_millis(i * 100), ""));
+        let track = timeline.add_track(events);
+        let [a, b, c] = ids.map(|id| KeyRef::new(track, id));
+
+        timeline.click_key(a, false, false);
+        assert_eq!(timeline.selection(), [a]);
+        timeline.click_key(b, false, true);
+        timeline.click_key(c, false, true);
+        assert_eq!(timeline.selection(), [a, b, c]);
+        // A plain click on a selected key keeps the selection to drag it.
+        timeline.click_key(b, false, false);
+        assert_eq!(timeline.selection(), [a, b, c]);
+        timeline.click_key(b, true, false);
+        assert_eq!(timeline.selection(), [a, c]);
+        timeline.click_key(b, true, false);
+        assert_eq!(timeline.selection(), [a, c, b]);
+        timeline.click_key(c, false, true);
+        assert_eq!(timeline.selection(), [a, c, b]);
+
+        timeline.clear_selection();
+        timeline.click_key(c, false, false);
+        assert_eq!(timeline.selection(), [c]);
+    }
+
+    #[test]
+    fn ruler_labels_do_not_overlap() {
+        let second = Time::TICKS_PER_SECOND;
+        let mut timeline = Timeline::new("test");
+        timeline.set_frame_rate(FrameRate::FPS_25);
+        // "-00:00:01" is 63 wide at 7 per character, 73 with the gap.
+        let narrow = |text: &str| text.len() as f32 * 7.0;
+        let wide = |text: &str| text.len() as f32 * 14.0;
+
+        assert_eq!(
+            timeline.choose_ruler_scale(100.0, narrow),
+            RulerScale {
+                major: TickStep::Ticks(second),
+                minor: TickStep::Ticks(second / 10),
+            }
+        );
+        assert_eq!(
+            timeline.choose_ruler_scale(100.0, wide),
+            RulerScale {
+                major: TickStep::Ticks(2 * second),
+                minor: TickStep::Ticks(2 * second / 10),
+            }
+        );
+        // Frames are 80 wide, labels of single frames need 94.
+        assert_eq!(
+            timeline.choose_ruler_scale(2000.0, narrow),
+            RulerScale {
+                major: TickStep::Frames(2),
+                minor: TickStep::Frames(1),
+            }
+        );
+        // Zoomed out beyond the coarsest step, labels overlap rather than vanish.
+        assert_eq!(
+            timeline.choose_ruler_scale(0.001, narrow),
+            RulerScale {
+                major: TickStep::Ticks(3600 * second),
+                minor: TickStep::Ticks(3600 * second),
+            }
+        );
+    }
+}